@@ -4,7 +4,41 @@ CREATE TABLE IF NOT EXISTS lios (
     provider_id VARCHAR(255) NOT NULL,
     station VARCHAR(255) NOT NULL,
     line VARCHAR(255) NOT NULL,
-    direction VARCHAR(255) NOT NULL
+    direction VARCHAR(255) NOT NULL,
+    foot_minutes_to_station INT NOT NULL DEFAULT 5,
+    foot_minutes_estimated BOOLEAN NOT NULL DEFAULT FALSE,
+    station_latitude DOUBLE NULL,
+    station_longitude DOUBLE NULL
+);
+
+-- Columns added after the first release, for databases created before them.
+ALTER TABLE lios ADD COLUMN IF NOT EXISTS foot_minutes_to_station INT NOT NULL DEFAULT 5;
+ALTER TABLE lios ADD COLUMN IF NOT EXISTS foot_minutes_estimated BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE lios ADD COLUMN IF NOT EXISTS station_latitude DOUBLE NULL;
+ALTER TABLE lios ADD COLUMN IF NOT EXISTS station_longitude DOUBLE NULL;
+
+CREATE TABLE IF NOT EXISTS boards (
+    id CHAR(36) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    latitude DOUBLE NULL,
+    longitude DOUBLE NULL
+);
+
+ALTER TABLE boards ADD COLUMN IF NOT EXISTS latitude DOUBLE NULL;
+ALTER TABLE boards ADD COLUMN IF NOT EXISTS longitude DOUBLE NULL;
+
+CREATE TABLE IF NOT EXISTS display_location (
+    id TINYINT PRIMARY KEY,
+    latitude DOUBLE NOT NULL,
+    longitude DOUBLE NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS board_lios (
+    board_id CHAR(36) NOT NULL,
+    lio_id CHAR(36) NOT NULL,
+    PRIMARY KEY (board_id, lio_id),
+    FOREIGN KEY (board_id) REFERENCES boards(id) ON DELETE CASCADE,
+    FOREIGN KEY (lio_id) REFERENCES lios(id) ON DELETE CASCADE
 );
 
 CREATE USER IF NOT EXISTS 'app'@'%' IDENTIFIED BY 'app_password';
@@ -0,0 +1,336 @@
+use std::{env, error::Error, fs, io::ErrorKind, path::PathBuf, str::FromStr, time::Duration};
+
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Proxy};
+use serde::{Deserialize, de::IntoDeserializer};
+
+use crate::services::{geo::WalkingModel, gtfs};
+
+/// Read when `CONFIG_PATH` is not set. Unlike an explicitly configured path it may be missing.
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+/// Upstream locations, HTTP client, server, polling, walking, clock and capture settings. Values are read from a TOML file and can be
+/// overridden by environment variables, see `config.example.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub wl: WlConfig,
+    pub oebb: OebbConfig,
+    pub gtfs: GtfsConfig,
+    pub http: HttpConfig,
+    pub server: ServerConfig,
+    pub timetable: TimetableConfig,
+    pub walking: WalkingConfig,
+    pub clock: ClockConfig,
+    pub capture: CaptureConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct WlConfig {
+    /// Base URL of the realtime API, `/monitor` is appended.
+    pub base_url: String,
+    pub stations_url: String,
+    /// Where the last downloaded station list is kept for starts while it cannot be downloaded.
+    pub stations_snapshot_path: PathBuf,
+    pub station_refresh_hours: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct OebbConfig {
+    /// Base URL of a HAFAS REST API, e.g. `/locations` and `/stops` are appended.
+    pub base_url: String,
+    /// How many stops are fetched at the same time.
+    pub max_concurrent_requests: usize,
+}
+
+/// A static GTFS feed, only used if `feed_path` is set.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct GtfsConfig {
+    /// Path to the feed's zip file.
+    pub feed_path: Option<String>,
+    pub provider_name: String,
+    /// URL of, or path to, a GTFS-Realtime feed applied on top of the static timetable.
+    pub realtime_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    pub timeout_seconds: u64,
+    pub connect_timeout_seconds: u64,
+    /// How long fetching the departures of a single stop may take before the stop counts as
+    /// failing. Stops are fetched concurrently, each with its own deadline.
+    pub fetch_deadline_seconds: u64,
+    pub user_agent: String,
+    /// Proxy for all upstream requests, e.g. `http://proxy.local:3128`.
+    pub proxy: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub listen_address: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct TimetableConfig {
+    /// How often the providers are polled for the trips of all LIOs.
+    pub poll_interval_seconds: u64,
+}
+
+/// Parameters of the walking time estimate, see [`WalkingModel`].
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct WalkingConfig {
+    pub speed_kmh: f64,
+    pub detour_factor: f64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ClockConfig {
+    /// Stops the clock at this moment, e.g. to test against recorded upstream responses. Replaying
+    /// captures freezes the clock at `capture.replay_at` instead.
+    pub frozen_at: Option<DateTime<Utc>>,
+}
+
+/// Recording of the Wiener Linien and ÖBB traffic, or replaying it to reproduce a timetable.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct CaptureConfig {
+    pub mode: CaptureModeConfig,
+    /// Directory captures are written to or replayed from.
+    pub dir: PathBuf,
+    /// Moment to replay, the time of the last capture if not set.
+    pub replay_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureModeConfig {
+    #[default]
+    Off,
+    Record,
+    Replay,
+}
+
+impl FromStr for CaptureModeConfig {
+    type Err = serde::de::value::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::deserialize(s.into_deserializer())
+    }
+}
+
+impl Default for WlConfig {
+    fn default() -> Self {
+        WlConfig {
+            base_url: "https://www.wienerlinien.at/ogd_realtime".to_string(),
+            stations_url: "https://www.wienerlinien.at/ogd_realtime/doku/ogd/wienerlinien-ogd-haltestellen.csv".to_string(),
+            stations_snapshot_path: PathBuf::from("wl-stations.json"),
+            station_refresh_hours: 24,
+        }
+    }
+}
+
+impl Default for OebbConfig {
+    fn default() -> Self {
+        OebbConfig {
+            base_url: "https://oebb.macistry.com/api".to_string(),
+            max_concurrent_requests: 4,
+        }
+    }
+}
+
+impl Default for GtfsConfig {
+    fn default() -> Self {
+        GtfsConfig {
+            feed_path: None,
+            provider_name: gtfs::DEFAULT_PROVIDER_NAME.to_string(),
+            realtime_url: None,
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            timeout_seconds: 10,
+            connect_timeout_seconds: 5,
+            fetch_deadline_seconds: 15,
+            user_agent: concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")).to_string(),
+            proxy: None,
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            listen_address: "0.0.0.0:3000".to_string(),
+        }
+    }
+}
+
+impl Default for TimetableConfig {
+    fn default() -> Self {
+        TimetableConfig {
+            poll_interval_seconds: 30,
+        }
+    }
+}
+
+impl Default for WalkingConfig {
+    fn default() -> Self {
+        let model = WalkingModel::default();
+
+        WalkingConfig {
+            speed_kmh: model.speed_kmh,
+            detour_factor: model.detour_factor,
+        }
+    }
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        CaptureConfig {
+            mode: CaptureModeConfig::Off,
+            dir: PathBuf::from("captures"),
+            replay_at: None,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file at `CONFIG_PATH` or `config.toml` and applies the environment
+    /// variable overrides.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let mut config = match env::var("CONFIG_PATH") {
+            Ok(path) => toml::from_str(&fs::read_to_string(path)?)?,
+            Err(_) => match fs::read_to_string(DEFAULT_CONFIG_PATH) {
+                Ok(content) => toml::from_str(&content)?,
+                Err(e) if e.kind() == ErrorKind::NotFound => Config::default(),
+                Err(e) => return Err(e.into()),
+            },
+        };
+
+        override_from_env(&mut config.wl.base_url, "WL_BASE_URL")?;
+        override_from_env(&mut config.wl.stations_url, "WL_STATIONS_URL")?;
+        override_from_env(
+            &mut config.wl.stations_snapshot_path,
+            "WL_STATIONS_SNAPSHOT_PATH",
+        )?;
+        override_from_env(
+            &mut config.wl.station_refresh_hours,
+            "STATION_REFRESH_HOURS",
+        )?;
+        override_from_env(&mut config.oebb.base_url, "OEBB_BASE_URL")?;
+        override_from_env(
+            &mut config.oebb.max_concurrent_requests,
+            "OEBB_MAX_CONCURRENT_REQUESTS",
+        )?;
+        if let Ok(path) = env::var("GTFS_FEED_PATH") {
+            config.gtfs.feed_path = Some(path);
+        }
+        override_from_env(&mut config.gtfs.provider_name, "GTFS_PROVIDER_NAME")?;
+        if let Ok(url) = env::var("GTFS_RT_URL") {
+            config.gtfs.realtime_url = Some(url);
+        }
+        override_from_env(&mut config.http.timeout_seconds, "HTTP_TIMEOUT_SECONDS")?;
+        override_from_env(
+            &mut config.http.connect_timeout_seconds,
+            "HTTP_CONNECT_TIMEOUT_SECONDS",
+        )?;
+        override_from_env(
+            &mut config.http.fetch_deadline_seconds,
+            "HTTP_FETCH_DEADLINE_SECONDS",
+        )?;
+        override_from_env(&mut config.http.user_agent, "HTTP_USER_AGENT")?;
+        if let Ok(proxy) = env::var("HTTP_PROXY_URL") {
+            config.http.proxy = Some(proxy);
+        }
+        override_from_env(&mut config.server.listen_address, "LISTEN_ADDRESS")?;
+        override_from_env(
+            &mut config.timetable.poll_interval_seconds,
+            "POLL_INTERVAL_SECONDS",
+        )?;
+        override_from_env(&mut config.walking.speed_kmh, "WALKING_SPEED_KMH")?;
+        override_from_env(&mut config.walking.detour_factor, "WALKING_DETOUR_FACTOR")?;
+        if let Ok(at) = env::var("CLOCK_FROZEN_AT") {
+            config.clock.frozen_at = Some(
+                at.parse()
+                    .map_err(|e| format!("Invalid value for CLOCK_FROZEN_AT: {}", e))?,
+            );
+        }
+        override_from_env(&mut config.capture.mode, "CAPTURE_MODE")?;
+        override_from_env(&mut config.capture.dir, "CAPTURE_DIR")?;
+        if let Ok(at) = env::var("REPLAY_AT") {
+            config.capture.replay_at = Some(
+                at.parse()
+                    .map_err(|e| format!("Invalid value for REPLAY_AT: {}", e))?,
+            );
+        }
+
+        // A speed of zero would make every station unreachable, a negative one every walk instant.
+        if config.walking.speed_kmh.is_nan() || config.walking.speed_kmh <= 0.0 {
+            return Err("walking.speed_kmh must be greater than zero".into());
+        }
+        if config.walking.detour_factor.is_nan() || config.walking.detour_factor <= 0.0 {
+            return Err("walking.detour_factor must be greater than zero".into());
+        }
+        // Intervals of zero would poll upstream in a tight loop, a deadline of zero fail every stop.
+        if config.timetable.poll_interval_seconds == 0 {
+            return Err("timetable.poll_interval_seconds must be greater than zero".into());
+        }
+        if config.wl.station_refresh_hours == 0 {
+            return Err("wl.station_refresh_hours must be greater than zero".into());
+        }
+        if config.http.fetch_deadline_seconds == 0 {
+            return Err("http.fetch_deadline_seconds must be greater than zero".into());
+        }
+
+        // Endpoints are appended with a leading slash.
+        config.wl.base_url = config.wl.base_url.trim_end_matches('/').to_string();
+        config.oebb.base_url = config.oebb.base_url.trim_end_matches('/').to_string();
+
+        Ok(config)
+    }
+
+    pub fn walking_model(&self) -> WalkingModel {
+        WalkingModel {
+            speed_kmh: self.walking.speed_kmh,
+            detour_factor: self.walking.detour_factor,
+        }
+    }
+
+    /// Builds the client shared by all upstream requests.
+    pub fn http_client(&self) -> Result<Client, reqwest::Error> {
+        let mut builder = Client::builder()
+            .timeout(Duration::from_secs(self.http.timeout_seconds))
+            .connect_timeout(Duration::from_secs(self.http.connect_timeout_seconds))
+            .user_agent(&self.http.user_agent);
+
+        if let Some(proxy) = &self.http.proxy {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+
+        builder.build()
+    }
+}
+
+fn override_from_env<T: FromStr>(value: &mut T, name: &str) -> Result<(), Box<dyn Error>>
+where
+    T::Err: Error + 'static,
+{
+    if let Ok(s) = env::var(name) {
+        *value = s
+            .parse()
+            .map_err(|e| format!("Invalid value for {}: {}", name, e))?;
+    }
+
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, FixedOffset, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
@@ -13,35 +14,224 @@ pub struct LioViewDto {
     pub station: String,
     pub line: String,
     pub direction: String,
+    pub foot_minutes_to_station: i32,
+    /// Whether the walking time is estimated from the display location instead of set by hand.
+    pub foot_minutes_estimated: bool,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct LioCreateDto {
     pub provider: String,
     pub station: String,
+    /// The provider's id of the station (DIVA for Wiener Linien, HAFAS stop id for ÖBB). When
+    /// given, `station` is only used as a label and not matched against station names.
+    pub provider_id: Option<String>,
     pub line: String,
     pub direction: String,
+    /// Estimated from the distance between the display and the station when left out.
+    pub foot_minutes_to_station: Option<i32>,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Debug, Deserialize)]
+pub struct LioUpdateDto {
+    /// Changing the provider without a `provider_id` matches `station` against its station names.
+    pub provider: Option<String>,
+    /// A new name is matched against the provider's station names, unless `provider_id` is given
+    /// too, which makes it only a label for that stop.
+    pub station: Option<String>,
+    pub provider_id: Option<String>,
+    pub line: Option<String>,
+    pub direction: Option<String>,
+    pub foot_minutes_to_station: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StationSearchQuery {
+    pub q: String,
+    pub provider: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StationSearchDto {
+    pub provider: String,
+    pub id: String,
+    pub name: String,
+    pub score: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NearbyStationsQuery {
+    pub lat: f64,
+    pub lon: f64,
+    /// Search radius in meters.
+    pub radius: Option<f64>,
+    pub provider: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NearbyStationDto {
+    pub provider: String,
+    pub id: String,
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub distance_meters: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatusDto {
+    pub stations: StationListStatusDto,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StationListStatusDto {
+    pub provider: String,
+    pub station_count: usize,
+    /// When the station list in use was downloaded, `None` if no list could be loaded at all.
+    pub fetched_at: Option<DateTime<Utc>>,
+    pub age_seconds: Option<i64>,
+    /// Why the last download failed, if it did.
+    pub refresh_error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StationRefreshDto {
+    pub station_count: usize,
+    pub added: Vec<StationDto>,
+    pub removed: Vec<StationDto>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StationDto {
+    pub provider: String,
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StationLineDto {
+    pub line: String,
+    pub direction: String,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct BoardViewDto {
+    pub id: String,
+    pub name: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BoardCreateDto {
+    pub name: String,
+    /// Location of the board's display, used instead of the server's display location to
+    /// estimate walking times on this board.
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct DisplayLocationDto {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BoardLioAddDto {
+    pub lio_id: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct TimetableDto {
     pub trips: Vec<TripDto>,
-    pub message: Option<String>
+    pub message: Option<String>,
+    pub alerts: Vec<AlertDto>,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct TripDto {
     pub line: String,
     pub direction: String,
     pub foot_minutes_to_station: i32,
-    pub departures: Vec<DepartureDto>
+    pub departures: Vec<DepartureDto>,
+    pub stale: bool,
+    pub age_seconds: Option<i64>,
+    /// Alerts affecting this trip, collected into `TimetableDto::alerts` and sent along with
+    /// websocket `trip` messages.
+    #[serde(skip)]
+    pub alerts: Vec<AlertDto>,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AlertDto {
+    pub id: String,
+    pub kind: AlertKind,
+    pub title: String,
+    pub description: Option<String>,
+    pub lines: Vec<String>,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    Disruption,
+    ElevatorOutage,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct DepartureDto {
     pub direction: Option<String>,
+    /// Scheduled departure time in Vienna local time.
+    pub planned_time: Option<DateTime<FixedOffset>>,
+    /// Realtime departure time in Vienna local time, missing without realtime data.
+    pub expected_time: Option<DateTime<FixedOffset>>,
     pub countdown: i32,
+    pub leave_in: i32,
     pub real_time: bool,
     pub late: bool,
-    pub traffic_jam: bool
+    pub traffic_jam: bool,
+    pub cancelled: bool,
+    pub platform: Option<String>,
+    /// Whether the departure leaves from another platform than planned.
+    pub platform_changed: bool,
+    pub delay_seconds: Option<i32>,
+    pub remarks: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsClientMessage {
+    Subscribe {
+        #[serde(default)]
+        lio_ids: Vec<String>,
+        board_id: Option<String>,
+    },
+    /// Unsubscribing from a board sends `removed` for its LIOs that are not subscribed directly.
+    Unsubscribe {
+        #[serde(default)]
+        lio_ids: Vec<String>,
+        board_id: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsServerMessage {
+    /// `alerts` are the trip's alerts, which `TripDto` itself leaves out.
+    Trip {
+        lio_id: String,
+        trip: TripDto,
+        alerts: Vec<AlertDto>,
+    },
+    Removed {
+        lio_id: String,
+    },
+    Error {
+        message: String,
+    },
 }
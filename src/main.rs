@@ -1,3 +1,4 @@
+mod config;
 mod dtos;
 mod models;
 mod services;
@@ -6,59 +7,179 @@ use axum::{
     BoxError, Router,
     error_handling::HandleErrorLayer,
     http::StatusCode,
-    routing::{delete, get},
+    routing::{delete, get, post},
 };
 use dotenvy::dotenv;
 use sqlx::MySqlPool;
-use std::{env, time::Duration};
+use std::{env, sync::Arc, time::Duration};
 use tower::ServiceBuilder;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::{
-    models::internal::Station,
+    config::{CaptureModeConfig, Config},
     services::{
-        internal::{create_lio, delete_lio, get_lio, get_timetable},
-        wl,
+        admin::refresh_stations,
+        boards::{
+            add_board_lio, create_board, delete_board, get_board, get_board_lios,
+            get_board_timetable, get_boards, remove_board_lio, update_board,
+        },
+        cache::TripCache,
+        capture::{CaptureMode, Replay, UpstreamClient},
+        clock::Clock,
+        display::{get_display_location, set_display_location},
+        geo::WalkingModel,
+        gtfs::{self, Gtfs},
+        gtfs_rt::GtfsRealtime,
+        internal::{create_lio, delete_lio, get_lio, get_timetable, update_lio},
+        oebb::Oebb,
+        provider::ProviderRegistry,
+        refresher,
+        stations::{get_nearby_stations, get_station_lines, search_stations},
+        status::get_status,
+        stream::stream_timetable,
+        wl::WienerLinien,
+        wl_stations::{self, StationStore},
+        ws::ws_handler,
     },
 };
 
 #[derive(Clone)]
 struct AppState {
     pool: MySqlPool,
-    stations: Vec<Station>,
+    providers: ProviderRegistry,
+    trip_cache: TripCache,
+    walking: WalkingModel,
+    wl_stations: StationStore,
+    upstream: UpstreamClient,
 }
 
 #[tokio::main]
 async fn main() {
-    let stations = wl::get_stations().await.unwrap();
-
     dotenv().ok();
 
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+                format!("{}=debug,tower_http=debug", env!("CARGO_CRATE_NAME")).into()
+            }),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let config = Config::load().expect("Failed to load config");
+    let http_client = config.http_client().expect("Failed to build HTTP client");
+
+    let configured_clock = config.clock.frozen_at.map_or(Clock::System, Clock::Frozen);
+    let capture_dir = config.capture.dir.clone();
+    let (capture_mode, clock) = match config.capture.mode {
+        CaptureModeConfig::Off => (CaptureMode::Off, configured_clock),
+        CaptureModeConfig::Record => {
+            std::fs::create_dir_all(&capture_dir).expect("Failed to create capture directory");
+            tracing::info!("Recording upstream traffic to {}", capture_dir.display());
+            (CaptureMode::Record(capture_dir), configured_clock)
+        }
+        CaptureModeConfig::Replay => {
+            let replay = Replay::load(&capture_dir, config.capture.replay_at)
+                .await
+                .expect("Failed to load captures");
+            tracing::info!(
+                "Replaying {} captured responses from {} as of {}",
+                replay.capture_count(),
+                capture_dir.display(),
+                replay.at().to_rfc3339()
+            );
+            let clock = Clock::Frozen(replay.at());
+            (CaptureMode::Replay(replay), clock)
+        }
+    };
+    let upstream = UpstreamClient::new(http_client.clone(), capture_mode, clock);
+
+    let wl_stations = StationStore::load(
+        &upstream,
+        config.wl.stations_url.clone(),
+        config.wl.stations_snapshot_path.clone(),
+    )
+    .await;
+
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
     let pool = MySqlPool::connect(&database_url)
         .await
         .expect("Failed to connect to MariaDB");
 
+    let mut providers = ProviderRegistry::default();
+    providers.register(WienerLinien::new(
+        wl_stations.clone(),
+        upstream.clone(),
+        config.wl.base_url.clone(),
+    ));
+    providers.register(Oebb::new(
+        upstream.clone(),
+        config.oebb.base_url.clone(),
+        clock,
+        config.oebb.max_concurrent_requests,
+        Duration::from_secs(config.http.fetch_deadline_seconds),
+    ));
+
+    if let Some(path) = &config.gtfs.feed_path {
+        let name = config.gtfs.provider_name.clone();
+        let feed = Arc::new(gtfs::load_feed(path, &name).expect("Failed to load GTFS feed"));
+
+        match &config.gtfs.realtime_url {
+            Some(feed_url) => providers.register(GtfsRealtime::new(
+                name,
+                feed,
+                feed_url.clone(),
+                http_client.clone(),
+                clock,
+            )),
+            None => providers.register(Gtfs::new(name, feed, clock)),
+        }
+    }
+
+    let poll_interval = Duration::from_secs(config.timetable.poll_interval_seconds);
+
     let state = AppState {
         pool: pool.clone(),
-        stations,
+        providers,
+        trip_cache: TripCache::new(
+            chrono::Duration::from_std(poll_interval * 3).unwrap(),
+            clock,
+        ),
+        walking: config.walking_model(),
+        wl_stations,
+        upstream,
     };
 
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-                format!("{}=debug,tower_http=debug", env!("CARGO_CRATE_NAME")).into()
-            }),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    let station_refresh_interval = Duration::from_secs(config.wl.station_refresh_hours * 60 * 60);
+
+    tokio::spawn(refresher::run(state.clone(), poll_interval));
+    tokio::spawn(wl_stations::run(state.clone(), station_refresh_interval));
 
     let app = Router::new()
+        .route("/status", get(get_status))
+        .route("/admin/stations/refresh", post(refresh_stations))
         .route("/timetable", get(get_timetable))
+        .route("/timetable/stream", get(stream_timetable))
         .route("/lio", get(get_lio).post(create_lio))
-        .route("/lio/{id}", delete(delete_lio))
+        .route("/lio/{id}", delete(delete_lio).patch(update_lio))
+        .route("/stations", get(search_stations))
+        .route("/stations/nearby", get(get_nearby_stations))
+        .route("/stations/{provider}/{id}/lines", get(get_station_lines))
+        .route(
+            "/display/location",
+            get(get_display_location).put(set_display_location),
+        )
+        .route("/boards", get(get_boards).post(create_board))
+        .route(
+            "/boards/{id}",
+            get(get_board).patch(update_board).delete(delete_board),
+        )
+        .route("/boards/{id}/lios", get(get_board_lios).post(add_board_lio))
+        .route("/boards/{id}/lios/{lio_id}", delete(remove_board_lio))
+        .route("/boards/{id}/timetable", get(get_board_timetable))
+        .route("/ws", get(ws_handler))
         // Add middleware to all routes
         .layer(
             ServiceBuilder::new()
@@ -78,7 +199,9 @@ async fn main() {
         )
         .with_state(state);
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+    let listener = tokio::net::TcpListener::bind(&config.server.listen_address)
+        .await
+        .unwrap();
 
     tracing::debug!("listening on {}", listener.local_addr().unwrap());
     let _ = axum::serve(listener, app).await;
@@ -1,3 +1,5 @@
+pub mod gtfs;
+pub mod gtfs_rt;
 pub mod internal;
 pub mod oebb;
 pub mod wl;
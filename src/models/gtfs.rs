@@ -0,0 +1,68 @@
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct AgencyRow {
+    pub agency_timezone: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StopRow {
+    pub stop_id: String,
+    pub stop_name: String,
+    #[serde(default)]
+    pub stop_lat: Option<f64>,
+    #[serde(default)]
+    pub stop_lon: Option<f64>,
+    #[serde(default)]
+    pub parent_station: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RouteRow {
+    pub route_id: String,
+    #[serde(default)]
+    pub route_short_name: Option<String>,
+    #[serde(default)]
+    pub route_long_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TripRow {
+    pub route_id: String,
+    pub service_id: String,
+    pub trip_id: String,
+    #[serde(default)]
+    pub trip_headsign: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StopTimeRow {
+    pub trip_id: String,
+    #[serde(default)]
+    pub arrival_time: Option<String>,
+    #[serde(default)]
+    pub departure_time: Option<String>,
+    pub stop_id: String,
+    pub stop_sequence: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarRow {
+    pub service_id: String,
+    pub monday: u8,
+    pub tuesday: u8,
+    pub wednesday: u8,
+    pub thursday: u8,
+    pub friday: u8,
+    pub saturday: u8,
+    pub sunday: u8,
+    pub start_date: String,
+    pub end_date: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarDateRow {
+    pub service_id: String,
+    pub date: String,
+    pub exception_type: u8,
+}
@@ -0,0 +1,136 @@
+//! Subset of `gtfs-realtime.proto` needed to read TripUpdates and ServiceAlerts.
+//! Field tags follow https://gtfs.org/realtime/reference/.
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FeedMessage {
+    #[prost(message, required, tag = "1")]
+    pub header: FeedHeader,
+    #[prost(message, repeated, tag = "2")]
+    pub entity: Vec<FeedEntity>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FeedHeader {
+    #[prost(string, required, tag = "1")]
+    pub gtfs_realtime_version: String,
+    #[prost(uint64, optional, tag = "3")]
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FeedEntity {
+    #[prost(string, required, tag = "1")]
+    pub id: String,
+    #[prost(bool, optional, tag = "2")]
+    pub is_deleted: Option<bool>,
+    #[prost(message, optional, tag = "3")]
+    pub trip_update: Option<TripUpdate>,
+    #[prost(message, optional, tag = "5")]
+    pub alert: Option<Alert>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TripUpdate {
+    #[prost(message, required, tag = "1")]
+    pub trip: TripDescriptor,
+    #[prost(message, repeated, tag = "2")]
+    pub stop_time_update: Vec<StopTimeUpdate>,
+    #[prost(uint64, optional, tag = "4")]
+    pub timestamp: Option<u64>,
+    #[prost(int32, optional, tag = "5")]
+    pub delay: Option<i32>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TripDescriptor {
+    #[prost(string, optional, tag = "1")]
+    pub trip_id: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub start_time: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub start_date: Option<String>,
+    #[prost(int32, optional, tag = "4")]
+    pub schedule_relationship: Option<i32>,
+    #[prost(string, optional, tag = "5")]
+    pub route_id: Option<String>,
+}
+
+/// `TripDescriptor.ScheduleRelationship` values that mean the trip does not run.
+pub const TRIP_CANCELED: i32 = 3;
+pub const TRIP_DELETED: i32 = 7;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StopTimeUpdate {
+    #[prost(uint32, optional, tag = "1")]
+    pub stop_sequence: Option<u32>,
+    #[prost(message, optional, tag = "2")]
+    pub arrival: Option<StopTimeEvent>,
+    #[prost(message, optional, tag = "3")]
+    pub departure: Option<StopTimeEvent>,
+    #[prost(string, optional, tag = "4")]
+    pub stop_id: Option<String>,
+    #[prost(int32, optional, tag = "5")]
+    pub schedule_relationship: Option<i32>,
+}
+
+/// `StopTimeUpdate.ScheduleRelationship` value for a stop the vehicle will not serve.
+pub const STOP_SKIPPED: i32 = 1;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StopTimeEvent {
+    #[prost(int32, optional, tag = "1")]
+    pub delay: Option<i32>,
+    #[prost(int64, optional, tag = "2")]
+    pub time: Option<i64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Alert {
+    #[prost(message, repeated, tag = "1")]
+    pub active_period: Vec<TimeRange>,
+    #[prost(message, repeated, tag = "5")]
+    pub informed_entity: Vec<EntitySelector>,
+    #[prost(int32, optional, tag = "7")]
+    pub effect: Option<i32>,
+    #[prost(message, optional, tag = "10")]
+    pub header_text: Option<TranslatedString>,
+    #[prost(message, optional, tag = "11")]
+    pub description_text: Option<TranslatedString>,
+}
+
+/// `Alert.Effect` value for alerts that suspend service of the informed entities.
+pub const EFFECT_NO_SERVICE: i32 = 1;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TimeRange {
+    #[prost(uint64, optional, tag = "1")]
+    pub start: Option<u64>,
+    #[prost(uint64, optional, tag = "2")]
+    pub end: Option<u64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct EntitySelector {
+    #[prost(string, optional, tag = "1")]
+    pub agency_id: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub route_id: Option<String>,
+    #[prost(message, optional, tag = "4")]
+    pub trip: Option<TripDescriptor>,
+    #[prost(string, optional, tag = "5")]
+    pub stop_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TranslatedString {
+    #[prost(message, repeated, tag = "1")]
+    pub translation: Vec<Translation>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Translation {
+    #[prost(string, required, tag = "1")]
+    pub text: String,
+    #[prost(string, optional, tag = "2")]
+    pub language: Option<String>,
+}
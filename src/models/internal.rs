@@ -1,16 +1,70 @@
+use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Station {
     pub id: String,
     pub name: String,
     pub provider: String,
+    pub location: Option<GeoPoint>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Lio {
+    pub provider: String,
+    pub provider_id: String,
+    pub station: String,
+    pub line: String,
+    pub direction: String,
+    pub foot_minutes_to_station: i32,
+    pub foot_minutes_estimated: bool,
+    pub station_latitude: Option<f64>,
+    pub station_longitude: Option<f64>,
 }
 
 #[derive(Debug, Clone, FromRow)]
 pub struct IntervalLio {
+    pub id: String,
     pub provider: String,
     pub provider_id: String,
     pub line: String,
     pub direction: String,
+    pub foot_minutes_to_station: i32,
+    pub foot_minutes_estimated: bool,
+    pub station_latitude: Option<f64>,
+    pub station_longitude: Option<f64>,
+}
+
+impl Lio {
+    pub fn station_location(&self) -> Option<GeoPoint> {
+        geo_point(self.station_latitude, self.station_longitude)
+    }
+}
+
+impl IntervalLio {
+    pub fn station_location(&self) -> Option<GeoPoint> {
+        geo_point(self.station_latitude, self.station_longitude)
+    }
+}
+
+/// Combines nullable coordinate columns into a point if both are set.
+pub fn geo_point(latitude: Option<f64>, longitude: Option<f64>) -> Option<GeoPoint> {
+    latitude
+        .zip(longitude)
+        .map(|(latitude, longitude)| GeoPoint {
+            latitude,
+            longitude,
+        })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StationLine {
+    pub line: String,
+    pub direction: String,
 }
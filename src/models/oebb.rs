@@ -5,12 +5,19 @@ pub struct Location {
     #[serde(rename = "type")]
     pub kind: String,
     pub id: String,
-    pub name: String
+    pub name: String,
+    pub location: Option<Coordinates>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Departures {
-    pub departures: Vec<Departure>
+    pub departures: Vec<Departure>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -19,10 +26,27 @@ pub struct Departure {
     #[serde(rename = "plannedWhen")]
     pub planned_when: String,
     pub direction: String,
-    pub line: Line
+    pub line: Line,
+    pub platform: Option<String>,
+    #[serde(rename = "plannedPlatform")]
+    pub planned_platform: Option<String>,
+    /// Delay in seconds, missing without realtime data.
+    pub delay: Option<i32>,
+    #[serde(default)]
+    pub cancelled: bool,
+    #[serde(default)]
+    pub remarks: Vec<Remark>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Remark {
+    /// `hint`, `status` or `warning`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub text: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Line {
-    pub name: String
+    pub name: String,
 }
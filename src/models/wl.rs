@@ -6,6 +6,10 @@ pub struct StationCsvRow {
     pub diva: String,
     #[serde(rename = "PlatformText")]
     pub platform_text: String,
+    #[serde(rename = "Longitude")]
+    pub longitude: Option<f64>,
+    #[serde(rename = "Latitude")]
+    pub latitude: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -17,6 +21,10 @@ pub struct MonitorResponse {
 #[derive(Debug, Deserialize)]
 pub struct Data {
     pub monitors: Vec<Monitor>,
+    #[serde(rename = "trafficInfos", default)]
+    pub traffic_infos: Vec<TrafficInfo>,
+    #[serde(rename = "trafficInfoCategories", default)]
+    pub traffic_info_categories: Vec<TrafficInfoCategory>,
 }
 
 // #[derive(Debug, Deserialize)]
@@ -28,17 +36,18 @@ pub struct Data {
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Monitor {
-    // pub locationStop: LocationStop,
+    #[serde(rename = "locationStop")]
+    pub location_stop: LocationStop,
     pub lines: Vec<Line>,
 }
 
-// #[derive(Debug, Deserialize)]
-// pub struct LocationStop {
-//     #[serde(rename = "type")]
-//     pub feature_type: String,
-//     pub geometry: Geometry,
-//     pub properties: StopProperties,
-// }
+#[derive(Debug, Deserialize, Clone)]
+pub struct LocationStop {
+    // #[serde(rename = "type")]
+    // pub feature_type: String,
+    // pub geometry: Geometry,
+    pub properties: StopProperties,
+}
 
 // #[derive(Debug, Deserialize)]
 // pub struct Geometry {
@@ -47,23 +56,24 @@ pub struct Monitor {
 //     pub coordinates: [f64; 2], // [longitude, latitude]
 // }
 
-// #[derive(Debug, Deserialize)]
-// pub struct StopProperties {
-//     pub name: String,
-//     pub title: String,
-//     pub municipality: String,
-//     pub municipalityId: i64,
-//     #[serde(rename = "type")]
-//     pub stop_type: String,
-//     pub coordName: String,
-//     pub gate: String,
-//     pub attributes: StopAttributes,
-// }
+#[derive(Debug, Deserialize, Clone)]
+pub struct StopProperties {
+    /// The DIVA number of the station.
+    pub name: String,
+    // pub title: String,
+    // pub municipality: String,
+    // pub municipalityId: i64,
+    // #[serde(rename = "type")]
+    // pub stop_type: String,
+    // pub coordName: String,
+    // pub gate: String,
+    pub attributes: StopAttributes,
+}
 
-// #[derive(Debug, Deserialize)]
-// pub struct StopAttributes {
-//     pub rbl: i32,
-// }
+#[derive(Debug, Deserialize, Clone)]
+pub struct StopAttributes {
+    pub rbl: Option<i64>,
+}
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Line {
@@ -105,3 +115,30 @@ pub struct Vehicle {
     #[serde(rename = "trafficjam")]
     pub traffic_jam: bool,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct TrafficInfo {
+    pub name: String,
+    #[serde(rename = "refTrafficInfoCategoryId")]
+    pub category_id: i32,
+    pub title: String,
+    pub description: Option<String>,
+    pub time: Option<TrafficInfoTime>,
+    #[serde(rename = "relatedLines", default)]
+    pub related_lines: Vec<String>,
+    /// RBL numbers of the affected platforms.
+    #[serde(rename = "relatedStops", default)]
+    pub related_stops: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrafficInfoTime {
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrafficInfoCategory {
+    pub id: i32,
+    pub name: String,
+}
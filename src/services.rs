@@ -1,3 +1,20 @@
+pub mod admin;
+pub mod boards;
+pub mod cache;
+pub mod capture;
+pub mod clock;
+pub mod display;
+pub mod geo;
+pub mod gtfs;
+pub mod gtfs_rt;
 pub mod internal;
 pub mod oebb;
+pub mod provider;
+pub mod refresher;
+pub mod search;
+pub mod stations;
+pub mod status;
+pub mod stream;
 pub mod wl;
+pub mod wl_stations;
+pub mod ws;
@@ -0,0 +1,41 @@
+use axum::{Json, extract::State};
+use reqwest::StatusCode;
+
+use crate::{
+    AppState,
+    dtos::internal::{ErrorDto, StationDto, StationRefreshDto},
+    models::internal::Station,
+    services::internal::error_response,
+};
+
+/// Downloads the Wiener Linien station list right away instead of waiting for the scheduled
+/// refresh.
+pub async fn refresh_stations(
+    State(app_state): State<AppState>,
+) -> Result<Json<StationRefreshDto>, (StatusCode, Json<ErrorDto>)> {
+    let changes = app_state
+        .wl_stations
+        .refresh(&app_state.upstream)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error refreshing Wiener Linien stations: {}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to fetch stations from Wiener Linien API.".to_string(),
+            )
+        })?;
+
+    Ok(Json(StationRefreshDto {
+        station_count: app_state.wl_stations.stations().len(),
+        added: changes.added.into_iter().map(station_to_dto).collect(),
+        removed: changes.removed.into_iter().map(station_to_dto).collect(),
+    }))
+}
+
+fn station_to_dto(station: Station) -> StationDto {
+    StationDto {
+        provider: station.provider,
+        id: station.id,
+        name: station.name,
+    }
+}
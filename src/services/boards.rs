@@ -0,0 +1,264 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use reqwest::StatusCode;
+use sqlx::MySqlPool;
+use uuid::Uuid;
+
+use crate::{
+    AppState,
+    dtos::internal::{BoardCreateDto, BoardLioAddDto, BoardViewDto, LioViewDto, TimetableDto},
+    models::internal::{GeoPoint, IntervalLio, geo_point},
+    services::{geo::is_valid, internal::build_timetable},
+};
+
+pub async fn get_boards(
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<BoardViewDto>>, StatusCode> {
+    let boards =
+        sqlx::query_as::<_, BoardViewDto>("SELECT id, name, latitude, longitude FROM boards")
+            .fetch_all(&app_state.pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(boards))
+}
+
+pub async fn get_board(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<BoardViewDto>, StatusCode> {
+    Ok(Json(find_board(&app_state.pool, &id).await?))
+}
+
+pub async fn create_board(
+    State(app_state): State<AppState>,
+    Json(input): Json<BoardCreateDto>,
+) -> Result<(StatusCode, Json<BoardViewDto>), StatusCode> {
+    validate_board_location(&input)?;
+
+    let id = Uuid::new_v4().to_string();
+
+    sqlx::query!(
+        "INSERT INTO boards (id, name, latitude, longitude) VALUES (?, ?, ?, ?)",
+        id,
+        input.name,
+        input.latitude,
+        input.longitude
+    )
+    .execute(&app_state.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(BoardViewDto {
+            id,
+            name: input.name,
+            latitude: input.latitude,
+            longitude: input.longitude,
+        }),
+    ))
+}
+
+pub async fn update_board(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+    Json(input): Json<BoardCreateDto>,
+) -> Result<Json<BoardViewDto>, StatusCode> {
+    validate_board_location(&input)?;
+    find_board(&app_state.pool, &id).await?;
+
+    sqlx::query!(
+        "UPDATE boards SET name = ?, latitude = ?, longitude = ? WHERE id = ?",
+        input.name,
+        input.latitude,
+        input.longitude,
+        id
+    )
+    .execute(&app_state.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(BoardViewDto {
+        id,
+        name: input.name,
+        latitude: input.latitude,
+        longitude: input.longitude,
+    }))
+}
+
+pub async fn delete_board(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    let res = sqlx::query!("DELETE FROM boards WHERE id = ?", id)
+        .execute(&app_state.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if res.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_board_lios(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<LioViewDto>>, StatusCode> {
+    find_board(&app_state.pool, &id).await?;
+
+    let lios = sqlx::query_as::<_, LioViewDto>(
+        r#"
+        SELECT l.id, l.provider, l.station, l.line, l.direction, l.foot_minutes_to_station,
+            l.foot_minutes_estimated
+        FROM lios l
+        JOIN board_lios bl ON bl.lio_id = l.id
+        WHERE bl.board_id = ?
+        "#,
+    )
+    .bind(&id)
+    .fetch_all(&app_state.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(lios))
+}
+
+pub async fn add_board_lio(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+    Json(input): Json<BoardLioAddDto>,
+) -> Result<StatusCode, StatusCode> {
+    find_board(&app_state.pool, &id).await?;
+
+    let lio_exists = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM lios WHERE id = ?")
+        .bind(&input.lio_id)
+        .fetch_one(&app_state.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        > 0;
+
+    if !lio_exists {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    sqlx::query!(
+        "INSERT IGNORE INTO board_lios (board_id, lio_id) VALUES (?, ?)",
+        id,
+        input.lio_id
+    )
+    .execute(&app_state.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn remove_board_lio(
+    State(app_state): State<AppState>,
+    Path((id, lio_id)): Path<(String, String)>,
+) -> Result<StatusCode, StatusCode> {
+    let res = sqlx::query!(
+        "DELETE FROM board_lios WHERE board_id = ? AND lio_id = ?",
+        id,
+        lio_id
+    )
+    .execute(&app_state.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if res.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_board_timetable(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<(StatusCode, Json<TimetableDto>), StatusCode> {
+    let board = find_board(&app_state.pool, &id).await?;
+
+    let lios = select_board_timetable_lios(&app_state, &board)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error loading LIOs of board {}: {}", id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok((StatusCode::OK, Json(build_timetable(&app_state, &lios))))
+}
+
+/// Loads the LIOs shown on a board. Estimated walking times are relative to the server's display
+/// location, boards with their own location estimate them from there instead.
+pub async fn select_board_timetable_lios(
+    app_state: &AppState,
+    board: &BoardViewDto,
+) -> Result<Vec<IntervalLio>, sqlx::Error> {
+    let mut lios = select_board_interval_lios(&app_state.pool, &board.id).await?;
+
+    if let Some(board_location) = geo_point(board.latitude, board.longitude) {
+        for lio in lios.iter_mut().filter(|lio| lio.foot_minutes_estimated) {
+            if let Some(station_location) = lio.station_location() {
+                lio.foot_minutes_to_station =
+                    app_state.walking.minutes(board_location, station_location);
+            }
+        }
+    }
+
+    Ok(lios)
+}
+
+async fn select_board_interval_lios(
+    pool: &MySqlPool,
+    board_id: &str,
+) -> Result<Vec<IntervalLio>, sqlx::Error> {
+    sqlx::query_as::<_, IntervalLio>(
+        r#"
+        SELECT l.id, l.provider, l.provider_id, l.line, l.direction, l.foot_minutes_to_station,
+            l.foot_minutes_estimated, l.station_latitude, l.station_longitude
+        FROM lios l
+        JOIN board_lios bl ON bl.lio_id = l.id
+        WHERE bl.board_id = ?
+        "#,
+    )
+    .bind(board_id)
+    .fetch_all(pool)
+    .await
+}
+
+async fn find_board(pool: &MySqlPool, id: &str) -> Result<BoardViewDto, StatusCode> {
+    select_board(pool, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+pub async fn select_board(pool: &MySqlPool, id: &str) -> Result<Option<BoardViewDto>, sqlx::Error> {
+    sqlx::query_as::<_, BoardViewDto>(
+        "SELECT id, name, latitude, longitude FROM boards WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+fn validate_board_location(input: &BoardCreateDto) -> Result<(), StatusCode> {
+    match (input.latitude, input.longitude) {
+        (None, None) => Ok(()),
+        (Some(latitude), Some(longitude))
+            if is_valid(GeoPoint {
+                latitude,
+                longitude,
+            }) =>
+        {
+            Ok(())
+        }
+        _ => Err(StatusCode::BAD_REQUEST),
+    }
+}
@@ -0,0 +1,225 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::watch;
+
+use crate::{
+    dtos::internal::TripDto,
+    models::internal::IntervalLio,
+    services::{clock::Clock, provider::TripResult},
+};
+
+struct CachedTrip {
+    fetched_at: DateTime<Utc>,
+    trip: TripDto,
+}
+
+/// The most recently fetched trip of every LIO, kept up to date by the refresher and used to
+/// fill in for unreachable providers.
+#[derive(Clone)]
+pub struct TripCache {
+    trips: Arc<RwLock<HashMap<String, CachedTrip>>>,
+    failing_providers: Arc<RwLock<HashSet<String>>>,
+    failing_lios: Arc<RwLock<HashSet<String>>>,
+    stale_after: Duration,
+    clock: Clock,
+    updates: Arc<watch::Sender<()>>,
+}
+
+impl TripCache {
+    /// Trips older than `stale_after` are reported as stale even if their provider is healthy.
+    pub fn new(stale_after: Duration, clock: Clock) -> Self {
+        TripCache {
+            trips: Arc::default(),
+            failing_providers: Arc::default(),
+            failing_lios: Arc::default(),
+            stale_after,
+            clock,
+            updates: Arc::new(watch::Sender::new(())),
+        }
+    }
+
+    /// Returns a receiver that is notified whenever a cached trip changes or a provider or LIO
+    /// starts or stops failing. Refreshes that bring nothing new notify nobody.
+    pub fn subscribe(&self) -> watch::Receiver<()> {
+        self.updates.subscribe()
+    }
+
+    /// Remembers freshly fetched trips and records the LIOs whose trip could not be fetched as
+    /// failing. `trips` must be in the order of `lios`.
+    pub fn store(&self, provider: &str, lios: &[&IntervalLio], trips: &[TripResult]) {
+        let now = self.clock.now();
+        let mut cached = self.trips.write().unwrap();
+        let mut failing_lios = self.failing_lios.write().unwrap();
+        let mut changed = false;
+
+        for (lio, trip) in lios.iter().zip(trips) {
+            match trip {
+                Ok(trip) => {
+                    // A trip that had gone stale is shown as fresh again.
+                    changed |= cached.get(&lio.id).is_none_or(|c| {
+                        c.trip != *trip
+                            || now.signed_duration_since(c.fetched_at) > self.stale_after
+                    });
+                    cached.insert(
+                        lio.id.clone(),
+                        CachedTrip {
+                            fetched_at: now,
+                            trip: trip.clone(),
+                        },
+                    );
+                    changed |= failing_lios.remove(&lio.id);
+                }
+                Err(_) => {
+                    changed |= failing_lios.insert(lio.id.clone());
+                }
+            }
+        }
+
+        changed |= self.failing_providers.write().unwrap().remove(provider);
+        if changed {
+            self.updates.send_replace(());
+        }
+    }
+
+    pub fn mark_failed(&self, provider: &str) {
+        let newly_failing = self
+            .failing_providers
+            .write()
+            .unwrap()
+            .insert(provider.to_string());
+        if newly_failing {
+            self.updates.send_replace(());
+        }
+    }
+
+    pub fn remove(&self, lio_id: &str) {
+        let removed = self.trips.write().unwrap().remove(lio_id).is_some();
+        let was_failing = self.failing_lios.write().unwrap().remove(lio_id);
+        if removed || was_failing {
+            self.updates.send_replace(());
+        }
+    }
+
+    /// Returns the cached trip of each LIO that has one, in the order of `lios`.
+    pub fn trips(&self, lios: &[&IntervalLio]) -> Vec<TripDto> {
+        lios.iter().filter_map(|lio| self.trip(lio)).collect()
+    }
+
+    /// Returns the cached trip of a LIO with countdowns moved forward by the time passed since it
+    /// was fetched, leaving out departures that can no longer be reached on foot. The trip is
+    /// marked stale if it or its provider is currently failing or it is older than `stale_after`.
+    pub fn trip(&self, lio: &IntervalLio) -> Option<TripDto> {
+        let now = self.clock.now();
+        let cached = self.trips.read().unwrap();
+        let entry = cached.get(&lio.id)?;
+
+        let age = now.signed_duration_since(entry.fetched_at);
+        let elapsed_minutes = age.num_minutes() as i32;
+
+        let mut trip = entry.trip.clone();
+        if self
+            .failing_providers
+            .read()
+            .unwrap()
+            .contains(&lio.provider)
+            || self.failing_lios.read().unwrap().contains(&lio.id)
+            || age > self.stale_after
+        {
+            trip.stale = true;
+            trip.age_seconds = Some(age.num_seconds());
+        }
+        trip.foot_minutes_to_station = lio.foot_minutes_to_station;
+        trip.departures.retain_mut(|d| {
+            d.countdown -= elapsed_minutes;
+            d.leave_in = d.countdown - lio.foot_minutes_to_station;
+            d.leave_in >= 0
+        });
+
+        Some(trip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lio(id: &str) -> IntervalLio {
+        IntervalLio {
+            id: id.to_string(),
+            provider: "wl".to_string(),
+            provider_id: "Stephansplatz".to_string(),
+            line: "U1".to_string(),
+            direction: "Leopoldau".to_string(),
+            foot_minutes_to_station: 2,
+            foot_minutes_estimated: false,
+            station_latitude: None,
+            station_longitude: None,
+        }
+    }
+
+    fn trip() -> TripDto {
+        TripDto {
+            line: "U1".to_string(),
+            direction: "Leopoldau".to_string(),
+            foot_minutes_to_station: 2,
+            departures: vec![],
+            stale: false,
+            age_seconds: None,
+            alerts: vec![],
+        }
+    }
+
+    fn cache() -> TripCache {
+        TripCache::new(
+            Duration::minutes(5),
+            Clock::Frozen("2025-07-10T06:00:00Z".parse().unwrap()),
+        )
+    }
+
+    #[test]
+    fn unchanged_refreshes_notify_nobody() {
+        let cache = cache();
+        let mut updates = cache.subscribe();
+        let lio = lio("lio-1");
+
+        cache.store("wl", &[&lio], &[Ok(trip())]);
+        assert!(updates.has_changed().unwrap());
+        updates.mark_unchanged();
+
+        cache.store("wl", &[&lio], &[Ok(trip())]);
+        assert!(!updates.has_changed().unwrap());
+
+        let mut changed = trip();
+        changed.direction = "Oberlaa".to_string();
+        cache.store("wl", &[&lio], &[Ok(changed)]);
+        assert!(updates.has_changed().unwrap());
+    }
+
+    #[test]
+    fn repeated_failures_notify_only_once() {
+        let cache = cache();
+        let mut updates = cache.subscribe();
+        let lio = lio("lio-1");
+
+        cache.mark_failed("wl");
+        assert!(updates.has_changed().unwrap());
+        updates.mark_unchanged();
+
+        cache.mark_failed("wl");
+        assert!(!updates.has_changed().unwrap());
+
+        cache.store("wl", &[&lio], &[Err("timeout".into())]);
+        assert!(updates.has_changed().unwrap());
+        updates.mark_unchanged();
+
+        cache.store("wl", &[&lio], &[Err("timeout".into())]);
+        assert!(!updates.has_changed().unwrap());
+
+        cache.remove("lio-2");
+        assert!(!updates.has_changed().unwrap());
+    }
+}
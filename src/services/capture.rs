@@ -0,0 +1,185 @@
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use uuid::Uuid;
+
+use crate::services::{clock::Clock, provider::ProviderError};
+
+/// An upstream request and its response as written to the capture directory.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Capture {
+    pub recorded_at: DateTime<Utc>,
+    pub url: String,
+    pub status: u16,
+    pub body: String,
+}
+
+pub enum CaptureMode {
+    /// Requests go to the upstream APIs.
+    Off,
+    /// Requests go to the upstream APIs and every response is written to the directory.
+    Record(PathBuf),
+    /// Requests are answered from previously recorded captures, see [`Replay`].
+    Replay(Replay),
+}
+
+/// Captures loaded for replay. A request is answered with the latest capture of its URL
+/// recorded at or before `at`, or the earliest one if it was only recorded later.
+pub struct Replay {
+    captures: Vec<Capture>,
+    at: DateTime<Utc>,
+}
+
+impl Replay {
+    /// Loads all captures in `dir`. Replays the moment of the last capture unless `at` is given.
+    pub async fn load(dir: &Path, at: Option<DateTime<Utc>>) -> Result<Self, Box<dyn Error>> {
+        let mut captures = Vec::new();
+        let mut entries = tokio::fs::read_dir(dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.path().extension().is_some_and(|e| e == "json") {
+                let content = tokio::fs::read_to_string(entry.path()).await?;
+                captures.push(
+                    serde_json::from_str::<Capture>(&content).map_err(|e| {
+                        format!("Invalid capture {}: {}", entry.path().display(), e)
+                    })?,
+                );
+            }
+        }
+        captures.sort_by_key(|c| c.recorded_at);
+
+        let at = at
+            .or(captures.last().map(|c| c.recorded_at))
+            .ok_or_else(|| format!("No captures found in {}", dir.display()))?;
+
+        Ok(Replay { captures, at })
+    }
+
+    pub fn capture_count(&self) -> usize {
+        self.captures.len()
+    }
+
+    /// The replayed moment, to freeze the clock at.
+    pub fn at(&self) -> DateTime<Utc> {
+        self.at
+    }
+
+    fn find(&self, url: &str) -> Option<&Capture> {
+        let matching = self.captures.iter().filter(|c| c.url == url);
+        let first = matching.clone().next();
+
+        matching
+            .take_while(|c| c.recorded_at <= self.at)
+            .last()
+            .or(first)
+    }
+}
+
+/// Client for the Wiener Linien and ÖBB APIs that records or replays their traffic depending on
+/// the capture mode.
+#[derive(Clone)]
+pub struct UpstreamClient {
+    client: Client,
+    mode: Arc<CaptureMode>,
+    clock: Clock,
+}
+
+/// A fully read upstream response.
+pub struct UpstreamResponse {
+    pub url: String,
+    pub status: StatusCode,
+    pub body: String,
+}
+
+impl UpstreamResponse {
+    pub fn error_for_status(self) -> Result<Self, ProviderError> {
+        if self.status.is_client_error() || self.status.is_server_error() {
+            return Err(format!("HTTP status {} for {}", self.status, self.url).into());
+        }
+
+        Ok(self)
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, ProviderError> {
+        serde_json::from_str(&self.body)
+            .map_err(|e| format!("Error decoding response from {}: {}", self.url, e).into())
+    }
+}
+
+impl UpstreamClient {
+    pub fn new(client: Client, mode: CaptureMode, clock: Clock) -> Self {
+        UpstreamClient {
+            client,
+            mode: Arc::new(mode),
+            clock,
+        }
+    }
+
+    pub async fn get(&self, url: &str) -> Result<UpstreamResponse, ProviderError> {
+        self.send(self.client.get(url)).await
+    }
+
+    pub async fn get_with_query<Q: Serialize + ?Sized>(
+        &self,
+        url: &str,
+        query: &Q,
+    ) -> Result<UpstreamResponse, ProviderError> {
+        self.send(self.client.get(url).query(query)).await
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<UpstreamResponse, ProviderError> {
+        let request = request.build()?;
+        let url = request.url().to_string();
+
+        if let CaptureMode::Replay(replay) = self.mode.as_ref() {
+            let capture = replay
+                .find(&url)
+                .ok_or_else(|| format!("No capture of {}", url))?;
+
+            return Ok(UpstreamResponse {
+                url,
+                status: StatusCode::from_u16(capture.status)?,
+                body: capture.body.clone(),
+            });
+        }
+
+        let resp = self.client.execute(request).await?;
+        let response = UpstreamResponse {
+            url,
+            status: resp.status(),
+            body: resp.text().await?,
+        };
+
+        if let CaptureMode::Record(dir) = self.mode.as_ref()
+            && let Err(e) = self.record(dir, &response).await
+        {
+            tracing::warn!("Error recording response of {}: {}", response.url, e);
+        }
+
+        Ok(response)
+    }
+
+    async fn record(&self, dir: &Path, response: &UpstreamResponse) -> Result<(), ProviderError> {
+        let capture = Capture {
+            recorded_at: self.clock.now(),
+            url: response.url.clone(),
+            status: response.status.as_u16(),
+            body: response.body.clone(),
+        };
+
+        // Sortable by time, the UUID keeps concurrent requests apart.
+        let file_name = format!(
+            "{}-{}.json",
+            capture.recorded_at.format("%Y%m%dT%H%M%S%.3fZ"),
+            Uuid::new_v4().simple()
+        );
+        tokio::fs::write(dir.join(file_name), serde_json::to_string_pretty(&capture)?).await?;
+
+        Ok(())
+    }
+}
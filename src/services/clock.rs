@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+
+/// Source of the current time for countdowns and cache ages. Frozen while replaying captured
+/// upstream traffic, so the timetable looks like it did when the traffic was recorded.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Clock {
+    #[default]
+    System,
+    Frozen(DateTime<Utc>),
+}
+
+impl Clock {
+    pub fn now(&self) -> DateTime<Utc> {
+        match self {
+            Clock::System => Utc::now(),
+            Clock::Frozen(at) => *at,
+        }
+    }
+}
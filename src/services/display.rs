@@ -0,0 +1,131 @@
+use axum::{Json, extract::State};
+use reqwest::StatusCode;
+use sqlx::MySqlPool;
+
+use crate::{
+    AppState,
+    dtos::internal::{DisplayLocationDto, ErrorDto},
+    models::internal::GeoPoint,
+    services::{
+        geo::is_valid,
+        internal::{error_response, select_interval_lios},
+    },
+};
+
+/// The display location is a single row with this id.
+const DISPLAY_LOCATION_ID: i8 = 1;
+
+pub async fn get_display_location(
+    State(app_state): State<AppState>,
+) -> Result<Json<DisplayLocationDto>, StatusCode> {
+    let location = display_location(&app_state.pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(DisplayLocationDto {
+        latitude: location.latitude,
+        longitude: location.longitude,
+    }))
+}
+
+/// Stores the display location and re-estimates the walking time of every LIO whose walking
+/// time was not set by hand.
+pub async fn set_display_location(
+    State(app_state): State<AppState>,
+    Json(input): Json<DisplayLocationDto>,
+) -> Result<Json<DisplayLocationDto>, (StatusCode, Json<ErrorDto>)> {
+    let location = GeoPoint {
+        latitude: input.latitude,
+        longitude: input.longitude,
+    };
+    if !is_valid(location) {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "Coordinates out of range.".to_string(),
+        ));
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO display_location (id, latitude, longitude)
+        VALUES (?, ?, ?)
+        ON DUPLICATE KEY UPDATE latitude = VALUES(latitude), longitude = VALUES(longitude)
+        "#,
+        DISPLAY_LOCATION_ID,
+        input.latitude,
+        input.longitude
+    )
+    .execute(&app_state.pool)
+    .await
+    .map_err(|_| {
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to store display location.".to_string(),
+        )
+    })?;
+
+    reestimate_foot_minutes(&app_state, location)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error re-estimating walking times: {}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to update walking times.".to_string(),
+            )
+        })?;
+
+    Ok(Json(input))
+}
+
+pub async fn display_location(pool: &MySqlPool) -> Result<Option<GeoPoint>, sqlx::Error> {
+    let location = sqlx::query_as::<_, DisplayLocationDto>(
+        "SELECT latitude, longitude FROM display_location WHERE id = ?",
+    )
+    .bind(DISPLAY_LOCATION_ID)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(location.map(|l| GeoPoint {
+        latitude: l.latitude,
+        longitude: l.longitude,
+    }))
+}
+
+/// Estimates the walking time from the display to a station, if both locations are known.
+pub async fn estimate_foot_minutes(
+    app_state: &AppState,
+    station_location: Option<GeoPoint>,
+) -> Result<Option<i32>, sqlx::Error> {
+    let Some(station_location) = station_location else {
+        return Ok(None);
+    };
+
+    Ok(display_location(&app_state.pool)
+        .await?
+        .map(|display| app_state.walking.minutes(display, station_location)))
+}
+
+async fn reestimate_foot_minutes(
+    app_state: &AppState,
+    display: GeoPoint,
+) -> Result<(), sqlx::Error> {
+    let lios = select_interval_lios(&app_state.pool).await?;
+
+    for lio in lios.iter().filter(|lio| lio.foot_minutes_estimated) {
+        let Some(station_location) = lio.station_location() else {
+            continue;
+        };
+        let foot_minutes_to_station = app_state.walking.minutes(display, station_location);
+
+        sqlx::query!(
+            "UPDATE lios SET foot_minutes_to_station = ? WHERE id = ?",
+            foot_minutes_to_station,
+            lio.id
+        )
+        .execute(&app_state.pool)
+        .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use crate::models::internal::{GeoPoint, Station};
+
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+/// Whether the point has a latitude within ±90° and a longitude within ±180°.
+pub fn is_valid(point: GeoPoint) -> bool {
+    (-90.0..=90.0).contains(&point.latitude) && (-180.0..=180.0).contains(&point.longitude)
+}
+
+/// Great-circle distance between two points in meters.
+pub fn distance_meters(a: GeoPoint, b: GeoPoint) -> f64 {
+    let d_lat = (b.latitude - a.latitude).to_radians();
+    let d_lon = (b.longitude - a.longitude).to_radians();
+
+    let h = (d_lat / 2.0).sin().powi(2)
+        + a.latitude.to_radians().cos()
+            * b.latitude.to_radians().cos()
+            * (d_lon / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_METERS * h.sqrt().asin()
+}
+
+/// Returns the stations with a known location within `radius_meters` of `location`.
+pub fn stations_within(
+    stations: &[Station],
+    location: GeoPoint,
+    radius_meters: f64,
+) -> Vec<Station> {
+    stations
+        .iter()
+        .filter(|s| {
+            s.location
+                .is_some_and(|l| distance_meters(location, l) <= radius_meters)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Estimates walking times from straight-line distances. Streets rarely lead straight to a
+/// station, so distances are stretched by `detour_factor`.
+#[derive(Clone, Copy, Debug)]
+pub struct WalkingModel {
+    pub speed_kmh: f64,
+    pub detour_factor: f64,
+}
+
+impl WalkingModel {
+    pub fn minutes(&self, from: GeoPoint, to: GeoPoint) -> i32 {
+        let meters = distance_meters(from, to) * self.detour_factor;
+        let meters_per_minute = self.speed_kmh * 1000.0 / 60.0;
+
+        (meters / meters_per_minute).ceil() as i32
+    }
+}
+
+impl Default for WalkingModel {
+    fn default() -> Self {
+        WalkingModel {
+            speed_kmh: 4.5,
+            detour_factor: 1.3,
+        }
+    }
+}
@@ -0,0 +1,594 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::{Europe::Vienna, Tz};
+use csv::ReaderBuilder;
+use serde::de::DeserializeOwned;
+use zip::ZipArchive;
+
+use crate::{
+    dtos::internal::{DepartureDto, TripDto},
+    models::{
+        gtfs::{AgencyRow, CalendarDateRow, CalendarRow, RouteRow, StopRow, StopTimeRow, TripRow},
+        internal::{GeoPoint, IntervalLio, Station, StationLine, geo_point},
+    },
+    services::{
+        clock::Clock,
+        geo::stations_within,
+        provider::{ProviderError, TransitProvider, TripResult},
+    },
+};
+
+pub const DEFAULT_PROVIDER_NAME: &str = "GTFS";
+
+/// How far ahead scheduled departures are looked up for the timetable.
+pub const TIMETABLE_LOOKAHEAD_MINUTES: i64 = 180;
+
+/// Maximum number of departures returned per trip, similar to what the realtime APIs return.
+pub const DEPARTURES_PER_TRIP: usize = 6;
+
+/// How far ahead departures are considered when listing the lines served at a station.
+const LINES_LOOKAHEAD_HOURS: i64 = 24;
+
+pub struct Gtfs {
+    name: String,
+    feed: Arc<GtfsFeed>,
+    clock: Clock,
+}
+
+impl Gtfs {
+    pub fn new(name: String, feed: Arc<GtfsFeed>, clock: Clock) -> Self {
+        Gtfs { name, feed, clock }
+    }
+}
+
+#[async_trait]
+impl TransitProvider for Gtfs {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn resolve_stations(&self, query: &str) -> Result<Vec<Station>, ProviderError> {
+        Ok(self
+            .feed
+            .stations()
+            .iter()
+            .filter(|s| s.name.to_lowercase().contains(&query.to_lowercase()))
+            .cloned()
+            .collect::<Vec<Station>>())
+    }
+
+    async fn station_candidates(&self, _query: &str) -> Result<Vec<Station>, ProviderError> {
+        Ok(self.feed.stations().to_vec())
+    }
+
+    async fn find_station(&self, station_id: &str) -> Result<Option<Station>, ProviderError> {
+        Ok(self
+            .feed
+            .stations()
+            .iter()
+            .find(|s| s.id == station_id)
+            .cloned())
+    }
+
+    async fn stations_nearby(
+        &self,
+        location: GeoPoint,
+        radius_meters: f64,
+    ) -> Result<Vec<Station>, ProviderError> {
+        Ok(stations_within(
+            self.feed.stations(),
+            location,
+            radius_meters,
+        ))
+    }
+
+    async fn lines_at_station(&self, station_id: &str) -> Result<Vec<StationLine>, ProviderError> {
+        let now = self.clock.now();
+
+        let mut lines = self
+            .feed
+            .scheduled_departures(
+                station_id,
+                now,
+                now + Duration::hours(LINES_LOOKAHEAD_HOURS),
+            )
+            .into_iter()
+            .map(|d| StationLine {
+                line: d.line,
+                direction: d.headsign,
+            })
+            .collect::<Vec<StationLine>>();
+        lines.sort();
+        lines.dedup();
+
+        Ok(lines)
+    }
+
+    async fn validate_line(
+        &self,
+        station_id: &str,
+        line: &str,
+        direction: &str,
+    ) -> Result<bool, ProviderError> {
+        Ok(self.lines_at_station(station_id).await?.iter().any(|l| {
+            l.line.to_lowercase() == line.to_lowercase()
+                && l.direction
+                    .to_lowercase()
+                    .contains(&direction.to_lowercase())
+        }))
+    }
+
+    async fn fetch_trips_for_lios(
+        &self,
+        lios: &[&IntervalLio],
+    ) -> Result<Vec<TripResult>, ProviderError> {
+        let now = self.clock.now();
+        let until = now + Duration::minutes(TIMETABLE_LOOKAHEAD_MINUTES);
+
+        Ok(lios
+            .iter()
+            .map(|lio| {
+                let departures = self
+                    .feed
+                    .scheduled_departures(&lio.provider_id, now, until)
+                    .into_iter()
+                    .filter(|d| d.matches_lio(lio))
+                    .take(DEPARTURES_PER_TRIP)
+                    .map(|d| {
+                        scheduled_departure_to_departure_dto(&d, now, lio.foot_minutes_to_station)
+                    })
+                    .collect::<Vec<DepartureDto>>();
+
+                Ok(TripDto {
+                    line: lio.line.clone(),
+                    direction: lio.direction.clone(),
+                    foot_minutes_to_station: lio.foot_minutes_to_station,
+                    departures,
+                    stale: false,
+                    age_seconds: None,
+                    alerts: vec![],
+                })
+            })
+            .collect::<Vec<TripResult>>())
+    }
+}
+
+fn scheduled_departure_to_departure_dto(
+    d: &ScheduledDeparture,
+    now: DateTime<Utc>,
+    foot_minutes_to_station: i32,
+) -> DepartureDto {
+    let countdown = d.time.signed_duration_since(now).num_minutes() as i32;
+
+    DepartureDto {
+        direction: Some(d.headsign.clone()),
+        planned_time: Some(d.time.with_timezone(&Vienna).fixed_offset()),
+        expected_time: None,
+        countdown,
+        leave_in: countdown - foot_minutes_to_station,
+        real_time: false,
+        late: false,
+        traffic_jam: false,
+        cancelled: false,
+        platform: None,
+        platform_changed: false,
+        delay_seconds: None,
+        remarks: vec![],
+    }
+}
+
+/// A departure taken from the static timetable of a GTFS feed.
+#[derive(Debug, Clone)]
+pub struct ScheduledDeparture {
+    pub trip_id: String,
+    pub route_id: String,
+    pub stop_id: String,
+    pub stop_sequence: u32,
+    pub service_day: NaiveDate,
+    pub line: String,
+    pub headsign: String,
+    pub time: DateTime<Utc>,
+}
+
+impl ScheduledDeparture {
+    pub fn matches_lio(&self, lio: &IntervalLio) -> bool {
+        self.line.to_lowercase() == lio.line.to_lowercase()
+            && self
+                .headsign
+                .to_lowercase()
+                .contains(&lio.direction.to_lowercase())
+    }
+}
+
+struct GtfsTrip {
+    route_id: String,
+    line: String,
+    service_id: String,
+    headsign: String,
+}
+
+struct GtfsStopTime {
+    trip_id: String,
+    stop_sequence: u32,
+    departure_seconds: u32,
+}
+
+#[derive(Default)]
+struct ServiceCalendar {
+    weekdays: [bool; 7],
+    range: Option<(NaiveDate, NaiveDate)>,
+    added: HashSet<NaiveDate>,
+    removed: HashSet<NaiveDate>,
+}
+
+impl ServiceCalendar {
+    fn is_active(&self, date: NaiveDate) -> bool {
+        if self.added.contains(&date) {
+            return true;
+        }
+        if self.removed.contains(&date) {
+            return false;
+        }
+
+        self.range
+            .is_some_and(|(start, end)| start <= date && date <= end)
+            && self.weekdays[date.weekday().num_days_from_monday() as usize]
+    }
+}
+
+/// Static timetable data of a GTFS feed, indexed for departure lookups by station.
+pub struct GtfsFeed {
+    timezone: Tz,
+    stations: Vec<Station>,
+    station_stops: HashMap<String, Vec<String>>,
+    trips: HashMap<String, GtfsTrip>,
+    stop_times: HashMap<String, Vec<GtfsStopTime>>,
+    services: HashMap<String, ServiceCalendar>,
+}
+
+impl GtfsFeed {
+    pub fn stations(&self) -> &[Station] {
+        &self.stations
+    }
+
+    /// Returns all departures from a station or any of its platforms within `[from, until)`,
+    /// ordered by departure time.
+    pub fn scheduled_departures(
+        &self,
+        station_id: &str,
+        from: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Vec<ScheduledDeparture> {
+        let Some(stop_ids) = self.station_stops.get(station_id) else {
+            return vec![];
+        };
+
+        // Trips running past midnight belong to the previous service day.
+        let first_day = from.with_timezone(&self.timezone).date_naive() - Duration::days(1);
+        let last_day = until.with_timezone(&self.timezone).date_naive();
+
+        let mut departures = Vec::new();
+        for day in first_day.iter_days().take_while(|d| *d <= last_day) {
+            let Some(day_start) = self.service_day_start(day) else {
+                continue;
+            };
+
+            for stop_id in stop_ids {
+                for stop_time in self.stop_times.get(stop_id).into_iter().flatten() {
+                    let time = day_start + Duration::seconds(stop_time.departure_seconds as i64);
+                    if time < from || time >= until {
+                        continue;
+                    }
+
+                    let Some(trip) = self.trips.get(&stop_time.trip_id) else {
+                        continue;
+                    };
+                    if !self
+                        .services
+                        .get(&trip.service_id)
+                        .is_some_and(|s| s.is_active(day))
+                    {
+                        continue;
+                    }
+
+                    departures.push(ScheduledDeparture {
+                        trip_id: stop_time.trip_id.clone(),
+                        route_id: trip.route_id.clone(),
+                        stop_id: stop_id.clone(),
+                        stop_sequence: stop_time.stop_sequence,
+                        service_day: day,
+                        line: trip.line.clone(),
+                        headsign: trip.headsign.clone(),
+                        time,
+                    });
+                }
+            }
+        }
+
+        departures.sort_by_key(|d| d.time);
+        departures
+    }
+
+    /// GTFS times are measured from "noon minus 12h" of the service day in the feed's timezone,
+    /// which only differs from midnight on days with a DST change.
+    fn service_day_start(&self, day: NaiveDate) -> Option<DateTime<Utc>> {
+        self.timezone
+            .from_local_datetime(&day.and_hms_opt(12, 0, 0)?)
+            .earliest()
+            .map(|noon| noon.with_timezone(&Utc) - Duration::hours(12))
+    }
+}
+
+/// Loads a GTFS zip feed from disk. Stations are all stops without a parent station; departures
+/// of a station include those of its child platforms.
+pub fn load_feed(path: &str, provider: &str) -> Result<GtfsFeed, ProviderError> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+
+    let timezone = read_csv::<AgencyRow>(&mut archive, "agency.txt")?
+        .first()
+        .and_then(|a| a.agency_timezone.parse::<Tz>().ok())
+        .unwrap_or(Vienna);
+
+    let mut stations = Vec::new();
+    let mut station_stops: HashMap<String, Vec<String>> = HashMap::new();
+    for stop in read_csv::<StopRow>(&mut archive, "stops.txt")? {
+        match stop.parent_station {
+            Some(parent) => station_stops.entry(parent).or_default().push(stop.stop_id),
+            None => {
+                station_stops
+                    .entry(stop.stop_id.clone())
+                    .or_default()
+                    .push(stop.stop_id.clone());
+                stations.push(Station {
+                    id: stop.stop_id,
+                    name: stop.stop_name,
+                    provider: provider.to_string(),
+                    location: geo_point(stop.stop_lat, stop.stop_lon),
+                });
+            }
+        }
+    }
+
+    let routes = read_csv::<RouteRow>(&mut archive, "routes.txt")?
+        .into_iter()
+        .map(|r| {
+            let name = r
+                .route_short_name
+                .filter(|n| !n.is_empty())
+                .or(r.route_long_name)
+                .unwrap_or_default();
+            (r.route_id, name)
+        })
+        .collect::<HashMap<String, String>>();
+
+    let trips = read_csv::<TripRow>(&mut archive, "trips.txt")?
+        .into_iter()
+        .map(|t| {
+            let trip = GtfsTrip {
+                line: routes.get(&t.route_id).cloned().unwrap_or_default(),
+                route_id: t.route_id,
+                service_id: t.service_id,
+                headsign: t.trip_headsign.unwrap_or_default(),
+            };
+            (t.trip_id, trip)
+        })
+        .collect::<HashMap<String, GtfsTrip>>();
+
+    let mut services: HashMap<String, ServiceCalendar> = HashMap::new();
+    if archive.index_for_name("calendar.txt").is_some() {
+        for c in read_csv::<CalendarRow>(&mut archive, "calendar.txt")? {
+            let service = services.entry(c.service_id).or_default();
+            service.weekdays = [
+                c.monday,
+                c.tuesday,
+                c.wednesday,
+                c.thursday,
+                c.friday,
+                c.saturday,
+                c.sunday,
+            ]
+            .map(|d| d == 1);
+            service.range = Some((parse_date(&c.start_date)?, parse_date(&c.end_date)?));
+        }
+    }
+    if archive.index_for_name("calendar_dates.txt").is_some() {
+        for c in read_csv::<CalendarDateRow>(&mut archive, "calendar_dates.txt")? {
+            let service = services.entry(c.service_id).or_default();
+            let date = parse_date(&c.date)?;
+            match c.exception_type {
+                1 => service.added.insert(date),
+                _ => service.removed.insert(date),
+            };
+        }
+    }
+
+    // Stop times are by far the largest file, so they are streamed instead of collected.
+    let mut stop_times: HashMap<String, Vec<GtfsStopTime>> = HashMap::new();
+    let mut last_stop_sequences: HashMap<String, u32> = HashMap::new();
+    for_each_csv_row::<StopTimeRow>(&mut archive, "stop_times.txt", |row| {
+        let last = last_stop_sequences.entry(row.trip_id.clone()).or_default();
+        *last = (*last).max(row.stop_sequence);
+
+        let Some(departure_seconds) = row
+            .departure_time
+            .or(row.arrival_time)
+            .and_then(|t| parse_gtfs_time(&t))
+        else {
+            return;
+        };
+        stop_times
+            .entry(row.stop_id)
+            .or_default()
+            .push(GtfsStopTime {
+                trip_id: row.trip_id,
+                stop_sequence: row.stop_sequence,
+                departure_seconds,
+            });
+    })?;
+
+    // Nobody departs at the final stop of a trip.
+    for stop_stop_times in stop_times.values_mut() {
+        stop_stop_times
+            .retain(|st| last_stop_sequences.get(&st.trip_id) != Some(&st.stop_sequence));
+    }
+
+    Ok(GtfsFeed {
+        timezone,
+        stations,
+        station_stops,
+        trips,
+        stop_times,
+        services,
+    })
+}
+
+fn read_csv<T: DeserializeOwned>(
+    archive: &mut ZipArchive<File>,
+    name: &str,
+) -> Result<Vec<T>, ProviderError> {
+    let mut rows = Vec::new();
+    for_each_csv_row(archive, name, |row| rows.push(row))?;
+    Ok(rows)
+}
+
+fn for_each_csv_row<T: DeserializeOwned>(
+    archive: &mut ZipArchive<File>,
+    name: &str,
+    mut f: impl FnMut(T),
+) -> Result<(), ProviderError> {
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(true)
+        .trim(csv::Trim::All)
+        .from_reader(archive.by_name(name)?);
+
+    for result in rdr.deserialize() {
+        f(result?);
+    }
+
+    Ok(())
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, ProviderError> {
+    Ok(NaiveDate::parse_from_str(date, "%Y%m%d")?)
+}
+
+/// Parses a GTFS `HH:MM:SS` time, which may exceed 24 hours for trips past midnight.
+fn parse_gtfs_time(time: &str) -> Option<u32> {
+    let mut parts = time.split(':').map(|p| p.parse::<u32>().ok());
+    let (h, m, s) = (parts.next()??, parts.next()??, parts.next()??);
+    Some(h * 3600 + m * 60 + s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(timezone: Tz) -> GtfsFeed {
+        GtfsFeed {
+            timezone,
+            stations: vec![],
+            station_stops: HashMap::new(),
+            trips: HashMap::new(),
+            stop_times: HashMap::new(),
+            services: HashMap::new(),
+        }
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_gtfs_time_accepts_times_past_midnight() {
+        assert_eq!(parse_gtfs_time("00:00:00"), Some(0));
+        assert_eq!(parse_gtfs_time("08:02:30"), Some(8 * 3600 + 2 * 60 + 30));
+        assert_eq!(parse_gtfs_time("24:10:00"), Some(24 * 3600 + 10 * 60));
+        assert_eq!(parse_gtfs_time("25:59:59"), Some(25 * 3600 + 59 * 60 + 59));
+    }
+
+    #[test]
+    fn parse_gtfs_time_rejects_malformed_times() {
+        assert_eq!(parse_gtfs_time(""), None);
+        assert_eq!(parse_gtfs_time("08:02"), None);
+        assert_eq!(parse_gtfs_time("08:xx:00"), None);
+    }
+
+    #[test]
+    fn service_is_active_on_its_weekdays_within_range() {
+        let service = ServiceCalendar {
+            // Weekdays only.
+            weekdays: [true, true, true, true, true, false, false],
+            range: Some((date(2025, 7, 1), date(2025, 7, 31))),
+            ..Default::default()
+        };
+
+        assert!(service.is_active(date(2025, 7, 10)));
+        assert!(!service.is_active(date(2025, 7, 12)));
+        assert!(!service.is_active(date(2025, 8, 1)));
+    }
+
+    #[test]
+    fn service_exceptions_override_the_calendar() {
+        let service = ServiceCalendar {
+            weekdays: [true, true, true, true, true, false, false],
+            range: Some((date(2025, 7, 1), date(2025, 7, 31))),
+            added: HashSet::from([date(2025, 7, 12), date(2025, 8, 2)]),
+            removed: HashSet::from([date(2025, 7, 10)]),
+        };
+
+        assert!(!service.is_active(date(2025, 7, 10)));
+        assert!(service.is_active(date(2025, 7, 12)));
+        assert!(service.is_active(date(2025, 8, 2)));
+    }
+
+    #[test]
+    fn service_without_calendar_only_runs_on_added_dates() {
+        let service = ServiceCalendar {
+            added: HashSet::from([date(2025, 7, 10)]),
+            ..Default::default()
+        };
+
+        assert!(service.is_active(date(2025, 7, 10)));
+        assert!(!service.is_active(date(2025, 7, 11)));
+    }
+
+    #[test]
+    fn service_day_starts_at_local_midnight_without_dst_change() {
+        let feed = feed(Vienna);
+
+        assert_eq!(
+            feed.service_day_start(date(2025, 7, 10)),
+            Some(utc("2025-07-09T22:00:00Z"))
+        );
+        assert_eq!(
+            feed.service_day_start(date(2025, 1, 15)),
+            Some(utc("2025-01-14T23:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn service_day_start_follows_noon_on_dst_changes() {
+        let feed = feed(Vienna);
+
+        // Clocks go forward at 02:00, noon is only 11 hours after midnight.
+        assert_eq!(
+            feed.service_day_start(date(2025, 3, 30)),
+            Some(utc("2025-03-29T22:00:00Z"))
+        );
+        // Clocks go back at 03:00, noon is 13 hours after midnight.
+        assert_eq!(
+            feed.service_day_start(date(2025, 10, 26)),
+            Some(utc("2025-10-25T23:00:00Z"))
+        );
+    }
+}
@@ -0,0 +1,736 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Europe::Vienna;
+use prost::Message;
+use reqwest::Client;
+
+use crate::{
+    dtos::internal::{AlertDto, AlertKind, DepartureDto, TripDto},
+    models::{
+        gtfs_rt::{
+            Alert, EFFECT_NO_SERVICE, EntitySelector, FeedMessage, STOP_SKIPPED, StopTimeUpdate,
+            TRIP_CANCELED, TRIP_DELETED, TimeRange, TranslatedString, TripUpdate,
+        },
+        internal::{GeoPoint, IntervalLio, Station, StationLine},
+    },
+    services::{
+        clock::Clock,
+        gtfs::{
+            DEPARTURES_PER_TRIP, Gtfs, GtfsFeed, ScheduledDeparture, TIMETABLE_LOOKAHEAD_MINUTES,
+        },
+        provider::{ProviderError, TransitProvider, TripResult},
+    },
+};
+
+/// How far into the past scheduled departures are considered, so delayed vehicles still show up.
+const MAX_DELAY_MINUTES: i64 = 30;
+
+/// Scheduled departures of a GTFS feed with a GTFS-Realtime feed of TripUpdates and
+/// ServiceAlerts applied on top.
+pub struct GtfsRealtime {
+    schedule: Gtfs,
+    feed: Arc<GtfsFeed>,
+    feed_url: String,
+    client: Client,
+    clock: Clock,
+}
+
+impl GtfsRealtime {
+    /// `feed_url` is either an `http(s)://` URL or a path to a protobuf file on disk.
+    pub fn new(
+        name: String,
+        feed: Arc<GtfsFeed>,
+        feed_url: String,
+        client: Client,
+        clock: Clock,
+    ) -> Self {
+        GtfsRealtime {
+            schedule: Gtfs::new(name, feed.clone(), clock),
+            feed,
+            feed_url,
+            client,
+            clock,
+        }
+    }
+}
+
+#[async_trait]
+impl TransitProvider for GtfsRealtime {
+    fn name(&self) -> &str {
+        self.schedule.name()
+    }
+
+    async fn resolve_stations(&self, query: &str) -> Result<Vec<Station>, ProviderError> {
+        self.schedule.resolve_stations(query).await
+    }
+
+    async fn station_candidates(&self, query: &str) -> Result<Vec<Station>, ProviderError> {
+        self.schedule.station_candidates(query).await
+    }
+
+    async fn find_station(&self, station_id: &str) -> Result<Option<Station>, ProviderError> {
+        self.schedule.find_station(station_id).await
+    }
+
+    async fn stations_nearby(
+        &self,
+        location: GeoPoint,
+        radius_meters: f64,
+    ) -> Result<Vec<Station>, ProviderError> {
+        self.schedule.stations_nearby(location, radius_meters).await
+    }
+
+    async fn lines_at_station(&self, station_id: &str) -> Result<Vec<StationLine>, ProviderError> {
+        self.schedule.lines_at_station(station_id).await
+    }
+
+    async fn validate_line(
+        &self,
+        station_id: &str,
+        line: &str,
+        direction: &str,
+    ) -> Result<bool, ProviderError> {
+        self.schedule
+            .validate_line(station_id, line, direction)
+            .await
+    }
+
+    async fn fetch_trips_for_lios(
+        &self,
+        lios: &[&IntervalLio],
+    ) -> Result<Vec<TripResult>, ProviderError> {
+        // The static timetable is still known when the realtime feed is not, so show that
+        // rather than letting the LIOs go stale.
+        let message = match fetch_feed(&self.client, &self.feed_url).await {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::warn!(
+                    "Error fetching GTFS-Realtime feed of {}, showing scheduled departures: {}",
+                    self.name(),
+                    e
+                );
+                return self.schedule.fetch_trips_for_lios(lios).await;
+            }
+        };
+        let now = self.clock.now();
+        let realtime = RealtimeIndex::new(&message, now);
+
+        let from = now - Duration::minutes(MAX_DELAY_MINUTES);
+        let until = now + Duration::minutes(TIMETABLE_LOOKAHEAD_MINUTES);
+
+        Ok(lios
+            .iter()
+            .map(|lio| {
+                let scheduled = self
+                    .feed
+                    .scheduled_departures(&lio.provider_id, from, until)
+                    .into_iter()
+                    .filter(|d| d.matches_lio(lio))
+                    .collect::<Vec<ScheduledDeparture>>();
+                let alerts = realtime.alerts_for(&scheduled);
+
+                let mut departures = scheduled
+                    .into_iter()
+                    .map(|d| realtime.apply(d))
+                    .filter(|d| d.expected >= now)
+                    .collect::<Vec<RealtimeDeparture>>();
+                departures.sort_by_key(|d| d.expected);
+
+                Ok(TripDto {
+                    line: lio.line.clone(),
+                    direction: lio.direction.clone(),
+                    foot_minutes_to_station: lio.foot_minutes_to_station,
+                    departures: departures
+                        .iter()
+                        .take(DEPARTURES_PER_TRIP)
+                        .map(|d| {
+                            realtime_departure_to_departure_dto(d, now, lio.foot_minutes_to_station)
+                        })
+                        .collect::<Vec<DepartureDto>>(),
+                    stale: false,
+                    age_seconds: None,
+                    alerts,
+                })
+            })
+            .collect::<Vec<TripResult>>())
+    }
+}
+
+pub async fn fetch_feed(client: &Client, feed_url: &str) -> Result<FeedMessage, ProviderError> {
+    let bytes = if feed_url.starts_with("http://") || feed_url.starts_with("https://") {
+        client
+            .get(feed_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?
+            .to_vec()
+    } else {
+        tokio::fs::read(feed_url).await?
+    };
+
+    Ok(FeedMessage::decode(bytes.as_slice())?)
+}
+
+struct RealtimeDeparture {
+    scheduled: ScheduledDeparture,
+    expected: DateTime<Utc>,
+    real_time: bool,
+    cancelled: bool,
+}
+
+fn realtime_departure_to_departure_dto(
+    d: &RealtimeDeparture,
+    now: DateTime<Utc>,
+    foot_minutes_to_station: i32,
+) -> DepartureDto {
+    let countdown = d.expected.signed_duration_since(now).num_minutes() as i32;
+
+    DepartureDto {
+        direction: Some(d.scheduled.headsign.clone()),
+        planned_time: Some(d.scheduled.time.with_timezone(&Vienna).fixed_offset()),
+        expected_time: d
+            .real_time
+            .then(|| d.expected.with_timezone(&Vienna).fixed_offset()),
+        countdown,
+        leave_in: countdown - foot_minutes_to_station,
+        real_time: d.real_time,
+        late: d.real_time && d.expected > d.scheduled.time,
+        traffic_jam: false,
+        cancelled: d.cancelled,
+        platform: None,
+        platform_changed: false,
+        delay_seconds: d.real_time.then(|| {
+            d.expected
+                .signed_duration_since(d.scheduled.time)
+                .num_seconds() as i32
+        }),
+        remarks: vec![],
+    }
+}
+
+/// TripUpdates by trip id and the currently active ServiceAlerts of a feed message, keyed by
+/// their entity id.
+struct RealtimeIndex<'a> {
+    trip_updates: HashMap<&'a str, Vec<&'a TripUpdate>>,
+    alerts: Vec<(&'a str, &'a Alert)>,
+    now: DateTime<Utc>,
+}
+
+impl<'a> RealtimeIndex<'a> {
+    fn new(message: &'a FeedMessage, now: DateTime<Utc>) -> Self {
+        let entities = message
+            .entity
+            .iter()
+            .filter(|e| !e.is_deleted.unwrap_or(false));
+
+        let mut trip_updates: HashMap<&str, Vec<&TripUpdate>> = HashMap::new();
+        let mut alerts = Vec::new();
+        for entity in entities {
+            if let Some(update) = &entity.trip_update
+                && let Some(trip_id) = &update.trip.trip_id
+            {
+                trip_updates.entry(trip_id).or_default().push(update);
+            }
+
+            if let Some(alert) = &entity.alert
+                && is_alert_active(alert, now)
+            {
+                alerts.push((entity.id.as_str(), alert));
+            }
+        }
+
+        RealtimeIndex {
+            trip_updates,
+            alerts,
+            now,
+        }
+    }
+
+    /// Returns the alerts informing about any of the given departures of a LIO.
+    fn alerts_for(&self, scheduled: &[ScheduledDeparture]) -> Vec<AlertDto> {
+        self.alerts
+            .iter()
+            .filter_map(|(id, alert)| {
+                let mut lines = scheduled
+                    .iter()
+                    .filter(|d| alert_selects_departure(alert, d))
+                    .map(|d| d.line.clone())
+                    .collect::<Vec<String>>();
+                lines.sort();
+                lines.dedup();
+
+                if lines.is_empty() {
+                    return None;
+                }
+
+                alert_to_alert_dto(id, alert, lines, self.now)
+            })
+            .collect()
+    }
+
+    fn apply(&self, scheduled: ScheduledDeparture) -> RealtimeDeparture {
+        let suspended = self.alerts.iter().any(|(_, alert)| {
+            alert.effect == Some(EFFECT_NO_SERVICE) && alert_selects_departure(alert, &scheduled)
+        });
+
+        let Some(update) = self.trip_update_for(&scheduled) else {
+            return RealtimeDeparture {
+                expected: scheduled.time,
+                real_time: false,
+                cancelled: suspended,
+                scheduled,
+            };
+        };
+
+        let trip_cancelled = matches!(
+            update.trip.schedule_relationship,
+            Some(TRIP_CANCELED) | Some(TRIP_DELETED)
+        );
+
+        let stop_update = update
+            .stop_time_update
+            .iter()
+            .find(|u| stop_time_update_matches(u, &scheduled));
+        let stop_skipped =
+            stop_update.is_some_and(|u| u.schedule_relationship == Some(STOP_SKIPPED));
+
+        RealtimeDeparture {
+            expected: expected_time(update, stop_update, &scheduled),
+            real_time: true,
+            cancelled: suspended || trip_cancelled || stop_skipped,
+            scheduled,
+        }
+    }
+
+    fn trip_update_for(&self, scheduled: &ScheduledDeparture) -> Option<&'a TripUpdate> {
+        let service_day = scheduled.service_day.format("%Y%m%d").to_string();
+
+        self.trip_updates
+            .get(scheduled.trip_id.as_str())?
+            .iter()
+            .find(|u| {
+                u.trip
+                    .start_date
+                    .as_ref()
+                    .is_none_or(|date| *date == service_day)
+            })
+            .copied()
+    }
+}
+
+fn stop_time_update_matches(update: &StopTimeUpdate, scheduled: &ScheduledDeparture) -> bool {
+    match update.stop_sequence {
+        Some(sequence) => sequence == scheduled.stop_sequence,
+        None => update.stop_id.as_ref() == Some(&scheduled.stop_id),
+    }
+}
+
+/// Determines the expected departure time at a stop. An update for the stop itself wins; otherwise
+/// the delay of the closest preceding stop is propagated, as the GTFS-RT spec describes, falling
+/// back to the delay of the whole trip.
+fn expected_time(
+    update: &TripUpdate,
+    stop_update: Option<&StopTimeUpdate>,
+    scheduled: &ScheduledDeparture,
+) -> DateTime<Utc> {
+    if let Some(event) = stop_update.and_then(|u| u.departure.as_ref().or(u.arrival.as_ref())) {
+        if let Some(time) = event.time.and_then(|t| DateTime::from_timestamp(t, 0)) {
+            return time;
+        }
+        if let Some(delay) = event.delay {
+            return scheduled.time + Duration::seconds(delay as i64);
+        }
+    }
+
+    let propagated_delay = update
+        .stop_time_update
+        .iter()
+        .filter(|u| u.stop_sequence.is_some_and(|s| s < scheduled.stop_sequence))
+        .max_by_key(|u| u.stop_sequence)
+        .and_then(|u| u.departure.as_ref().or(u.arrival.as_ref()))
+        .and_then(|e| e.delay);
+
+    match propagated_delay.or(update.delay) {
+        Some(delay) => scheduled.time + Duration::seconds(delay as i64),
+        None => scheduled.time,
+    }
+}
+
+fn alert_selects_departure(alert: &Alert, scheduled: &ScheduledDeparture) -> bool {
+    alert
+        .informed_entity
+        .iter()
+        .any(|e| entity_selects_departure(e, scheduled))
+}
+
+fn entity_selects_departure(entity: &EntitySelector, scheduled: &ScheduledDeparture) -> bool {
+    let trip_id = entity.trip.as_ref().and_then(|t| t.trip_id.as_ref());
+
+    if entity.route_id.is_none() && entity.stop_id.is_none() && trip_id.is_none() {
+        return false;
+    }
+
+    entity
+        .route_id
+        .as_ref()
+        .is_none_or(|id| *id == scheduled.route_id)
+        && entity
+            .stop_id
+            .as_ref()
+            .is_none_or(|id| *id == scheduled.stop_id)
+        && trip_id.is_none_or(|id| *id == scheduled.trip_id)
+}
+
+fn is_alert_active(alert: &Alert, now: DateTime<Utc>) -> bool {
+    alert.active_period.is_empty()
+        || alert
+            .active_period
+            .iter()
+            .any(|period| is_period_active(period, now))
+}
+
+fn is_period_active(period: &TimeRange, now: DateTime<Utc>) -> bool {
+    let now = now.timestamp() as u64;
+
+    period.start.is_none_or(|start| start <= now) && period.end.is_none_or(|end| now <= end)
+}
+
+/// Converts an alert into a disruption, valid for the active period it is currently in. Alerts
+/// without a header have nothing to show and are left out.
+fn alert_to_alert_dto(
+    id: &str,
+    alert: &Alert,
+    lines: Vec<String>,
+    now: DateTime<Utc>,
+) -> Option<AlertDto> {
+    let period = alert
+        .active_period
+        .iter()
+        .find(|period| is_period_active(period, now));
+    let to_date_time = |t: u64| DateTime::from_timestamp(t as i64, 0);
+
+    Some(AlertDto {
+        id: id.to_string(),
+        kind: AlertKind::Disruption,
+        title: alert.header_text.as_ref().and_then(translated_text)?,
+        description: alert.description_text.as_ref().and_then(translated_text),
+        lines,
+        valid_from: period.and_then(|p| p.start).and_then(to_date_time),
+        valid_until: period.and_then(|p| p.end).and_then(to_date_time),
+    })
+}
+
+/// Picks the German translation of a text, falling back to the first one.
+fn translated_text(text: &TranslatedString) -> Option<String> {
+    text.translation
+        .iter()
+        .find(|t| t.language.as_deref() == Some("de"))
+        .or(text.translation.first())
+        .map(|t| t.text.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::models::gtfs_rt::{
+        FeedEntity, FeedHeader, StopTimeEvent, Translation, TripDescriptor,
+    };
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn scheduled(stop_sequence: u32) -> ScheduledDeparture {
+        ScheduledDeparture {
+            trip_id: "trip-1".to_string(),
+            route_id: "route-U1".to_string(),
+            stop_id: "stop-1".to_string(),
+            stop_sequence,
+            service_day: NaiveDate::from_ymd_opt(2025, 7, 10).unwrap(),
+            line: "U1".to_string(),
+            headsign: "Leopoldau".to_string(),
+            time: utc("2025-07-10T06:10:00Z"),
+        }
+    }
+
+    fn stop_update(stop_sequence: u32, delay: Option<i32>, time: Option<i64>) -> StopTimeUpdate {
+        StopTimeUpdate {
+            stop_sequence: Some(stop_sequence),
+            departure: Some(StopTimeEvent { delay, time }),
+            ..Default::default()
+        }
+    }
+
+    fn trip_update(stop_time_update: Vec<StopTimeUpdate>, delay: Option<i32>) -> TripUpdate {
+        TripUpdate {
+            trip: TripDescriptor {
+                trip_id: Some("trip-1".to_string()),
+                start_date: Some("20250710".to_string()),
+                ..Default::default()
+            },
+            stop_time_update,
+            delay,
+            ..Default::default()
+        }
+    }
+
+    fn alert(effect: i32, informed_entity: EntitySelector, active_period: Vec<TimeRange>) -> Alert {
+        Alert {
+            active_period,
+            informed_entity: vec![informed_entity],
+            effect: Some(effect),
+            header_text: Some(TranslatedString {
+                translation: vec![
+                    Translation {
+                        text: "Line U1 interrupted".to_string(),
+                        language: Some("en".to_string()),
+                    },
+                    Translation {
+                        text: "Linie U1 unterbrochen".to_string(),
+                        language: Some("de".to_string()),
+                    },
+                ],
+            }),
+            description_text: None,
+        }
+    }
+
+    fn route_selector(route_id: &str) -> EntitySelector {
+        EntitySelector {
+            route_id: Some(route_id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn feed(trip_updates: Vec<TripUpdate>, alerts: Vec<Alert>) -> FeedMessage {
+        let entity = trip_updates
+            .into_iter()
+            .map(|update| FeedEntity {
+                trip_update: Some(update),
+                ..Default::default()
+            })
+            .chain(alerts.into_iter().map(|alert| FeedEntity {
+                alert: Some(alert),
+                ..Default::default()
+            }))
+            .enumerate()
+            .map(|(i, entity)| FeedEntity {
+                id: format!("entity-{}", i),
+                ..entity
+            })
+            .collect();
+
+        FeedMessage {
+            header: FeedHeader {
+                gtfs_realtime_version: "2.0".to_string(),
+                timestamp: None,
+            },
+            entity,
+        }
+    }
+
+    #[test]
+    fn expected_time_prefers_the_stops_own_update() {
+        let time = utc("2025-07-10T06:14:00Z");
+        let update = trip_update(vec![stop_update(5, Some(60), Some(time.timestamp()))], None);
+
+        assert_eq!(
+            expected_time(&update, update.stop_time_update.first(), &scheduled(5)),
+            time
+        );
+
+        let update = trip_update(vec![stop_update(5, Some(120), None)], None);
+        assert_eq!(
+            expected_time(&update, update.stop_time_update.first(), &scheduled(5)),
+            utc("2025-07-10T06:12:00Z")
+        );
+    }
+
+    #[test]
+    fn expected_time_propagates_the_delay_of_the_closest_preceding_stop() {
+        let update = trip_update(
+            vec![
+                stop_update(1, Some(60), None),
+                stop_update(3, Some(180), None),
+                stop_update(7, Some(600), None),
+            ],
+            Some(30),
+        );
+
+        assert_eq!(
+            expected_time(&update, None, &scheduled(5)),
+            utc("2025-07-10T06:13:00Z")
+        );
+    }
+
+    #[test]
+    fn expected_time_falls_back_to_the_trip_delay_and_schedule() {
+        let update = trip_update(vec![stop_update(7, Some(600), None)], Some(90));
+        assert_eq!(
+            expected_time(&update, None, &scheduled(5)),
+            utc("2025-07-10T06:11:30Z")
+        );
+
+        let update = trip_update(vec![], None);
+        assert_eq!(
+            expected_time(&update, None, &scheduled(5)),
+            scheduled(5).time
+        );
+    }
+
+    #[test]
+    fn departures_without_trip_update_keep_their_schedule() {
+        let message = feed(vec![], vec![]);
+        let index = RealtimeIndex::new(&message, utc("2025-07-10T06:00:00Z"));
+
+        let departure = index.apply(scheduled(5));
+        assert_eq!(departure.expected, scheduled(5).time);
+        assert!(!departure.real_time);
+        assert!(!departure.cancelled);
+    }
+
+    #[test]
+    fn cancelled_trips_and_skipped_stops_are_cancelled() {
+        let now = utc("2025-07-10T06:00:00Z");
+
+        let mut cancelled = trip_update(vec![], None);
+        cancelled.trip.schedule_relationship = Some(TRIP_CANCELED);
+        let message = feed(vec![cancelled], vec![]);
+        assert!(
+            RealtimeIndex::new(&message, now)
+                .apply(scheduled(5))
+                .cancelled
+        );
+
+        let mut skipped = stop_update(5, None, None);
+        skipped.schedule_relationship = Some(STOP_SKIPPED);
+        let message = feed(vec![trip_update(vec![skipped], None)], vec![]);
+        let index = RealtimeIndex::new(&message, now);
+        assert!(index.apply(scheduled(5)).cancelled);
+        assert!(!index.apply(scheduled(6)).cancelled);
+    }
+
+    #[test]
+    fn trip_updates_of_other_service_days_are_ignored() {
+        let mut update = trip_update(vec![], Some(300));
+        update.trip.start_date = Some("20250709".to_string());
+        let message = feed(vec![update], vec![]);
+
+        let departure =
+            RealtimeIndex::new(&message, utc("2025-07-10T06:00:00Z")).apply(scheduled(5));
+        assert!(!departure.real_time);
+        assert_eq!(departure.expected, scheduled(5).time);
+    }
+
+    #[test]
+    fn active_no_service_alerts_cancel_the_departures_they_select() {
+        let now = utc("2025-07-10T06:00:00Z");
+        let active = vec![TimeRange {
+            start: Some(now.timestamp() as u64 - 60),
+            end: Some(now.timestamp() as u64 + 60),
+        }];
+        let expired = vec![TimeRange {
+            start: None,
+            end: Some(now.timestamp() as u64 - 60),
+        }];
+
+        let message = feed(
+            vec![],
+            vec![alert(EFFECT_NO_SERVICE, route_selector("route-U1"), active)],
+        );
+        assert!(
+            RealtimeIndex::new(&message, now)
+                .apply(scheduled(5))
+                .cancelled
+        );
+
+        let message = feed(
+            vec![],
+            vec![alert(
+                EFFECT_NO_SERVICE,
+                route_selector("route-U1"),
+                expired,
+            )],
+        );
+        assert!(
+            !RealtimeIndex::new(&message, now)
+                .apply(scheduled(5))
+                .cancelled
+        );
+
+        let message = feed(
+            vec![],
+            vec![alert(EFFECT_NO_SERVICE, route_selector("route-U2"), vec![])],
+        );
+        assert!(
+            !RealtimeIndex::new(&message, now)
+                .apply(scheduled(5))
+                .cancelled
+        );
+
+        // Other effects only inform about the departure.
+        let message = feed(
+            vec![],
+            vec![alert(
+                EFFECT_NO_SERVICE + 1,
+                route_selector("route-U1"),
+                vec![],
+            )],
+        );
+        assert!(
+            !RealtimeIndex::new(&message, now)
+                .apply(scheduled(5))
+                .cancelled
+        );
+    }
+
+    #[test]
+    fn selectors_without_route_stop_or_trip_match_nothing() {
+        let selector = EntitySelector {
+            agency_id: Some("agency".to_string()),
+            ..Default::default()
+        };
+
+        assert!(!entity_selects_departure(&selector, &scheduled(5)));
+    }
+
+    #[test]
+    fn active_alerts_of_a_lios_departures_become_disruptions() {
+        let now = utc("2025-07-10T06:00:00Z");
+        let message = feed(
+            vec![],
+            vec![
+                alert(
+                    EFFECT_NO_SERVICE + 1,
+                    route_selector("route-U1"),
+                    vec![TimeRange {
+                        start: Some(now.timestamp() as u64 - 60),
+                        end: None,
+                    }],
+                ),
+                alert(EFFECT_NO_SERVICE, route_selector("route-U2"), vec![]),
+            ],
+        );
+
+        let alerts = RealtimeIndex::new(&message, now).alerts_for(&[scheduled(5)]);
+
+        assert_eq!(
+            alerts,
+            vec![AlertDto {
+                id: "entity-0".to_string(),
+                kind: AlertKind::Disruption,
+                title: "Linie U1 unterbrochen".to_string(),
+                description: None,
+                lines: vec!["U1".to_string()],
+                valid_from: Some(now - Duration::seconds(60)),
+                valid_until: None,
+            }]
+        );
+    }
+}
@@ -2,28 +2,34 @@ use axum::{
     Json,
     extract::{Path, State},
 };
-use reqwest::{Client, StatusCode};
+use reqwest::StatusCode;
+use sqlx::MySqlPool;
 use uuid::Uuid;
 
 use crate::{
     AppState,
-    dtos::internal::{ErrorDto, LioCreateDto, LioViewDto, TimetableDto, TripDto},
-    models::{
-        internal::{IntervalLio, Station},
-        oebb::Departure,
-        wl::{Monitor, MonitorResponse},
+    dtos::internal::{
+        AlertDto, ErrorDto, LioCreateDto, LioUpdateDto, LioViewDto, TimetableDto, TripDto,
     },
-    services::{oebb, wl},
+    models::internal::{GeoPoint, IntervalLio, Lio, Station},
+    services::{display::estimate_foot_minutes, refresher::refresh_lios},
 };
 
+/// Walking time used for LIOs created without one.
+const DEFAULT_FOOT_MINUTES_TO_STATION: i32 = 5;
+
 pub async fn get_lio(
     State(app_state): State<AppState>,
 ) -> Result<Json<Vec<LioViewDto>>, StatusCode> {
-    let lios =
-        sqlx::query_as::<_, LioViewDto>("SELECT id, provider, station, line, direction FROM lios")
-            .fetch_all(&app_state.pool)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let lios = sqlx::query_as::<_, LioViewDto>(
+        r#"
+        SELECT id, provider, station, line, direction, foot_minutes_to_station, foot_minutes_estimated
+        FROM lios
+        "#,
+    )
+    .fetch_all(&app_state.pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(Json(lios))
 }
@@ -32,245 +38,387 @@ pub async fn create_lio(
     State(app_state): State<AppState>,
     Json(input): Json<LioCreateDto>,
 ) -> Result<(StatusCode, Json<LioViewDto>), (StatusCode, Json<ErrorDto>)> {
-    if input.provider.as_str() == "Wiener Linien" {
-        let mut found_stations = app_state
-            .stations
-            .iter()
-            .filter(|s| {
-                s.name
-                    .to_lowercase()
-                    .contains(&input.station.to_lowercase())
-                    && s.provider == input.provider
-            })
-            .collect::<Vec<&Station>>();
-
-        if found_stations.len() == 0 {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorDto {
-                    message: format!(
-                        "Station '{}' with provider '{}' not found.",
-                        input.station, input.provider
-                    ),
-                }),
-            ));
-        } else if found_stations.len() > 1 {
-            let exact_maches = found_stations
-                .iter()
-                .filter(|s| s.name.to_lowercase() == input.station.to_lowercase())
-                .collect::<Vec<&&Station>>();
-
-            if exact_maches.len() == 1 {
-                found_stations = vec![exact_maches[0]];
-            } else {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(ErrorDto {
-                        message: format!(
-                            "Multiple stations found matching '{}' with provider '{}'. Please be more specific. Found stations: {:?}",
-                            input.station,
-                            input.provider,
-                            found_stations
-                                .iter()
-                                .map(|s| &s.name)
-                                .collect::<Vec<&String>>()
-                        ),
-                    }),
-                ));
-            }
-        }
+    if let Some(foot_minutes_to_station) = input.foot_minutes_to_station {
+        validate_foot_minutes_to_station(foot_minutes_to_station)?;
+    }
 
-        let station = found_stations[0];
+    let station = resolve_lio_station(
+        &app_state,
+        &input.provider,
+        &input.station,
+        input.provider_id.as_deref(),
+        &input.line,
+        &input.direction,
+    )
+    .await?;
 
-        let resp = Client::new()
-            .get(format!(
-                "https://www.wienerlinien.at/ogd_realtime/monitor?diva={}",
-                station.id
-            ))
-            .send()
-            .await
-            .map_err(|_| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorDto {
-                        message: "Failed to fetch data from Wiener Linien API.".to_string(),
-                    }),
-                )
-            })?
-            .json::<MonitorResponse>()
-            .await
-            .map_err(|_| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorDto {
-                        message: "Failed to parse response from Wiener Linien API.".to_string(),
-                    }),
-                )
-            })?;
-
-        let lines = resp
-            .data
-            .monitors
-            .iter()
-            .filter(|monitor| {
-                monitor.lines.iter().any(|line| {
-                    line.name.to_lowercase() == input.line.to_lowercase()
-                        && line
-                            .towards
-                            .to_lowercase()
-                            .contains(&input.direction.to_lowercase())
-                })
-            })
-            .collect::<Vec<&Monitor>>();
-
-        if lines.len() == 0 {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorDto {
-                    message: format!(
-                        "Line '{}' with direction '{}' not found at station '{}'.",
-                        input.line, input.direction, station.name
-                    ),
-                }),
-            ));
+    let foot_minutes_estimated = input.foot_minutes_to_station.is_none();
+    let foot_minutes_to_station = match input.foot_minutes_to_station {
+        Some(foot_minutes_to_station) => foot_minutes_to_station,
+        None => {
+            estimate_foot_minutes_or(
+                &app_state,
+                station.location,
+                DEFAULT_FOOT_MINUTES_TO_STATION,
+            )
+            .await?
         }
+    };
+    let station_latitude = station.location.map(|l| l.latitude);
+    let station_longitude = station.location.map(|l| l.longitude);
 
-        let id = Uuid::new_v4().to_string();
+    let id = Uuid::new_v4().to_string();
 
-        let create_result = sqlx::query!(
-            r#"
-        INSERT INTO lios (id, provider, provider_id, station, line, direction)
-        VALUES (?, ?, ?, ?, ?, ?)
+    sqlx::query!(
+        r#"
+        INSERT INTO lios (
+            id, provider, provider_id, station, line, direction, foot_minutes_to_station,
+            foot_minutes_estimated, station_latitude, station_longitude
+        )
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#,
+        id,
+        input.provider,
+        station.id,
+        input.station,
+        input.line,
+        input.direction,
+        foot_minutes_to_station,
+        foot_minutes_estimated,
+        station_latitude,
+        station_longitude
+    )
+    .execute(&app_state.pool)
+    .await
+    .map_err(|_| {
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to create LIO.".to_string(),
+        )
+    })?;
+
+    fetch_lio_trip(
+        &app_state,
+        IntervalLio {
+            id: id.clone(),
+            provider: input.provider.clone(),
+            provider_id: station.id,
+            line: input.line.clone(),
+            direction: input.direction.clone(),
+            foot_minutes_to_station,
+            foot_minutes_estimated,
+            station_latitude,
+            station_longitude,
+        },
+    )
+    .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(LioViewDto {
             id,
-            input.provider,
-            station.id,
-            input.station,
-            input.line,
-            input.direction
+            provider: input.provider,
+            station: input.station,
+            line: input.line,
+            direction: input.direction,
+            foot_minutes_to_station,
+            foot_minutes_estimated,
+        }),
+    ))
+}
+
+pub async fn update_lio(
+    State(app_state): State<AppState>,
+    Path(id): Path<String>,
+    Json(input): Json<LioUpdateDto>,
+) -> Result<Json<LioViewDto>, (StatusCode, Json<ErrorDto>)> {
+    let lio = sqlx::query_as::<_, Lio>(
+        r#"
+        SELECT provider, provider_id, station, line, direction, foot_minutes_to_station,
+            foot_minutes_estimated, station_latitude, station_longitude
+        FROM lios
+        WHERE id = ?
+        "#,
+    )
+    .bind(&id)
+    .fetch_optional(&app_state.pool)
+    .await
+    .map_err(|_| {
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to load LIO.".to_string(),
+        )
+    })?
+    .ok_or_else(|| error_response(StatusCode::NOT_FOUND, format!("LIO '{}' not found.", id)))?;
+
+    if let Some(foot_minutes_to_station) = input.foot_minutes_to_station {
+        validate_foot_minutes_to_station(foot_minutes_to_station)?;
+    }
+
+    let provider = input.provider.unwrap_or(lio.provider.clone());
+    let station = input.station.unwrap_or(lio.station.clone());
+    let line = input.line.unwrap_or(lio.line.clone());
+    let direction = input.direction.unwrap_or(lio.direction.clone());
+
+    // A new station name is resolved again like in `create_lio`. Sent along with a provider id,
+    // the name is only a label for that stop.
+    let station_renamed = station != lio.station;
+    let station_changed = provider != lio.provider
+        || match input.provider_id.as_ref() {
+            Some(provider_id) => *provider_id != lio.provider_id,
+            None => station_renamed,
+        };
+    let changed = station_changed || line != lio.line || direction != lio.direction;
+
+    let (provider_id, station_location) = if changed {
+        // A new provider or station name without a provider id is matched by name.
+        let known_provider_id = match input.provider_id.as_deref() {
+            Some(provider_id) => Some(provider_id),
+            None if provider == lio.provider && !station_renamed => Some(lio.provider_id.as_str()),
+            None => None,
+        };
+        let resolved = resolve_lio_station(
+            &app_state,
+            &provider,
+            &station,
+            known_provider_id,
+            &line,
+            &direction,
+        )
+        .await?;
+        (resolved.id, resolved.location)
+    } else {
+        (lio.provider_id.clone(), lio.station_location())
+    };
+
+    let (foot_minutes_to_station, foot_minutes_estimated) = match input.foot_minutes_to_station {
+        Some(foot_minutes_to_station) => (foot_minutes_to_station, false),
+        None if lio.foot_minutes_estimated && station_changed => (
+            estimate_foot_minutes_or(&app_state, station_location, lio.foot_minutes_to_station)
+                .await?,
+            true,
+        ),
+        None => (lio.foot_minutes_to_station, lio.foot_minutes_estimated),
+    };
+    let station_latitude = station_location.map(|l| l.latitude);
+    let station_longitude = station_location.map(|l| l.longitude);
+
+    sqlx::query!(
+        r#"
+        UPDATE lios
+        SET provider = ?, provider_id = ?, station = ?, line = ?, direction = ?,
+            foot_minutes_to_station = ?, foot_minutes_estimated = ?,
+            station_latitude = ?, station_longitude = ?
+        WHERE id = ?
+        "#,
+        provider,
+        provider_id,
+        station,
+        line,
+        direction,
+        foot_minutes_to_station,
+        foot_minutes_estimated,
+        station_latitude,
+        station_longitude,
+        id
+    )
+    .execute(&app_state.pool)
+    .await
+    .map_err(|_| {
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to update LIO.".to_string(),
+        )
+    })?;
+
+    if changed {
+        app_state.trip_cache.remove(&id);
+        fetch_lio_trip(
+            &app_state,
+            IntervalLio {
+                id: id.clone(),
+                provider: provider.clone(),
+                provider_id,
+                line: line.clone(),
+                direction: direction.clone(),
+                foot_minutes_to_station,
+                foot_minutes_estimated,
+                station_latitude,
+                station_longitude,
+            },
         )
-        .execute(&app_state.pool)
         .await;
+    }
 
-        return match create_result {
-            Ok(_) => Ok((
-                StatusCode::CREATED,
-                Json(LioViewDto {
-                    id,
-                    provider: input.provider,
-                    station: input.station,
-                    line: input.line,
-                    direction: input.direction,
-                }),
-            )),
-            Err(_e) => Err((
+    Ok(Json(LioViewDto {
+        id,
+        provider,
+        station,
+        line,
+        direction,
+        foot_minutes_to_station,
+        foot_minutes_estimated,
+    }))
+}
+
+/// Fetches the trip of a new or changed LIO right away instead of waiting for the refresher. If
+/// its provider cannot be reached the LIO stays without departures until the next refresh.
+async fn fetch_lio_trip(app_state: &AppState, lio: IntervalLio) {
+    refresh_lios(app_state, &[&lio]).await;
+}
+
+fn validate_foot_minutes_to_station(
+    foot_minutes_to_station: i32,
+) -> Result<(), (StatusCode, Json<ErrorDto>)> {
+    if foot_minutes_to_station < 0 {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "Walking time to the station must not be negative.".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Estimates the walking time from the display to a station, falling back to `fallback` while
+/// either location is unknown.
+async fn estimate_foot_minutes_or(
+    app_state: &AppState,
+    station_location: Option<GeoPoint>,
+    fallback: i32,
+) -> Result<i32, (StatusCode, Json<ErrorDto>)> {
+    let estimate = estimate_foot_minutes(app_state, station_location)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error loading display location: {}", e);
+            error_response(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorDto {
-                    message: "Failed to create LIO.".to_string(),
-                }),
-            )),
-        };
-    } else if input.provider.as_str() == "OEBB" {
-        let stations = oebb::fetch_stations(input.station.clone()).await.unwrap();
+                "Failed to load display location.".to_string(),
+            )
+        })?;
 
-        if stations.len() > 1 {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorDto {
-                    message: format!(
-                        "Multiple stations found matching '{}' with provider '{}'. Please be more specific. Found stations: {:?}",
-                        input.station,
-                        input.provider,
-                        stations.iter().map(|s| &s.name).collect::<Vec<&String>>()
-                    ),
-                }),
-            ));
-        }
+    Ok(estimate.unwrap_or(fallback))
+}
 
-        let departures = oebb::fetch_depatures_for_stations(vec![stations[0].id.clone()])
+/// Resolves a LIO's station at its provider, matching it by name unless its provider id is
+/// already known, and checks that the line departs there in the given direction.
+async fn resolve_lio_station(
+    app_state: &AppState,
+    provider_name: &str,
+    station_name: &str,
+    provider_id: Option<&str>,
+    line: &str,
+    direction: &str,
+) -> Result<Station, (StatusCode, Json<ErrorDto>)> {
+    let provider = app_state.providers.get(provider_name).ok_or_else(|| {
+        error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Provider '{}' not supported", provider_name),
+        )
+    })?;
+
+    let station = match provider_id {
+        Some(provider_id) => provider
+            .find_station(provider_id)
             .await
             .map_err(|e| {
-                println!("Error fetching departures: {}", e);
-                (
+                tracing::error!("Error looking up station: {}", e);
+                error_response(
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorDto {
-                        message: format!(
-                            "Failed to fetch departures from OEBB API: {}",
-                            e.to_string()
-                        ),
-                    }),
+                    format!("Failed to fetch stations from {} API.", provider.name()),
+                )
+            })?
+            .ok_or_else(|| {
+                error_response(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "Station id '{}' with provider '{}' not found.",
+                        provider_id, provider_name
+                    ),
+                )
+            })?,
+        None => {
+            let stations = provider.resolve_stations(station_name).await.map_err(|e| {
+                tracing::error!("Error resolving stations: {}", e);
+                error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to fetch stations from {} API.", provider.name()),
                 )
             })?;
 
-        let desired_departures = departures
-            .iter()
-            .filter(|d| {
-                d.direction
-                    .to_lowercase()
-                    .contains(&input.direction.to_lowercase())
-                    && d.line
-                        .name
-                        .replace(" ", "")
-                        .to_lowercase()
-                        .contains(&input.line.to_lowercase())
-            })
-            .collect::<Vec<&Departure>>();
-
-        if desired_departures.len() == 0 {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorDto {
-                    message: format!(
-                        "Line '{}' with direction '{}' not found at station '{}'.",
-                        input.line, input.direction, stations[0].name
-                    ),
-                }),
-            ));
+            select_station(stations, station_name, provider_name)?
         }
+    };
 
-        let id = Uuid::new_v4().to_string();
+    let line_found = provider
+        .validate_line(&station.id, line, direction)
+        .await
+        .map_err(|e| {
+            tracing::error!("Error fetching lines: {}", e);
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to fetch departures from {} API.", provider.name()),
+            )
+        })?;
 
-        let create_result = sqlx::query!(
-            r#"
-        INSERT INTO lios (id, provider, provider_id, station, line, direction)
-        VALUES (?, ?, ?, ?, ?, ?)
-        "#,
-            id,
-            input.provider,
-            stations[0].id,
-            input.station,
-            input.line,
-            input.direction
-        )
-        .execute(&app_state.pool)
-        .await;
+    if !line_found {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Line '{}' with direction '{}' not found at station '{}'.",
+                line, direction, station_name
+            ),
+        ));
+    }
 
-        return match create_result {
-            Ok(_) => Ok((
-                StatusCode::CREATED,
-                Json(LioViewDto {
-                    id,
-                    provider: input.provider,
-                    station: input.station,
-                    line: input.line,
-                    direction: input.direction,
-                }),
-            )),
-            Err(_e) => Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorDto {
-                    message: "Failed to create LIO.".to_string(),
-                }),
-            )),
-        };
-    } else {
-        return Err((
+    Ok(station)
+}
+
+/// Picks the single station matching the query, preferring an exact name match when the
+/// provider returned several candidates.
+fn select_station(
+    mut stations: Vec<Station>,
+    query: &str,
+    provider: &str,
+) -> Result<Station, (StatusCode, Json<ErrorDto>)> {
+    stations.retain(|s| s.provider == provider);
+
+    if stations.is_empty() {
+        return Err(error_response(
             StatusCode::BAD_REQUEST,
-            Json(ErrorDto {
-                message: format!("Provider '{}' not supported", input.provider),
-            }),
+            format!(
+                "Station '{}' with provider '{}' not found.",
+                query, provider
+            ),
         ));
     }
+
+    if stations.len() > 1 {
+        let exact_matches = stations
+            .iter()
+            .filter(|s| s.name.to_lowercase() == query.to_lowercase())
+            .count();
+
+        if exact_matches != 1 {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Multiple stations found matching '{}' with provider '{}'. Please be more specific. Found stations: {:?}",
+                    query,
+                    provider,
+                    stations.iter().map(|s| &s.name).collect::<Vec<&String>>()
+                ),
+            ));
+        }
+
+        stations.retain(|s| s.name.to_lowercase() == query.to_lowercase());
+    }
+
+    Ok(stations.remove(0))
+}
+
+pub fn error_response(status: StatusCode, message: String) -> (StatusCode, Json<ErrorDto>) {
+    (status, Json(ErrorDto { message }))
 }
 
 pub async fn delete_lio(
@@ -286,53 +434,97 @@ pub async fn delete_lio(
         return Err(StatusCode::NOT_FOUND);
     }
 
+    app_state.trip_cache.remove(&id);
+
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn get_timetable(
     State(app_state): State<AppState>,
 ) -> Result<(StatusCode, Json<TimetableDto>), StatusCode> {
-    let lios =
-        sqlx::query_as::<_, IntervalLio>("SELECT provider, provider_id, line, direction FROM lios")
-            .fetch_all(&app_state.pool)
-            .await
-            .map_err(|e| {
-                eprintln!("{:?}", e);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
+    let lios = select_interval_lios(&app_state.pool).await.map_err(|e| {
+        eprintln!("{:?}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
-    let wl_lios = lios
-        .iter()
-        .filter(|lio| lio.provider.as_str() == "Wiener Linien")
-        .collect::<Vec<&IntervalLio>>();
-
-    let oebb_lios = lios
-        .iter()
-        .filter(|lio| lio.provider.as_str() == "OEBB")
-        .collect::<Vec<&IntervalLio>>();
-
-    let mut trips = [
-        wl::fetch_trips_for_lios(&wl_lios).await.map_err(|e| {
-            eprintln!("{:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?,
-        oebb::fetch_trips_for_lios(&oebb_lios).await.map_err(|e| {
-            eprintln!("{:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?,
-    ]
-    .iter()
-    .flatten()
-    .cloned()
-    .collect::<Vec<TripDto>>();
-
-    trips.sort_by(|t1, t2| t1.departures[0].countdown.cmp(&t2.departures[0].countdown));
+    Ok((StatusCode::OK, Json(build_timetable(&app_state, &lios))))
+}
 
-    Ok((
-        StatusCode::OK,
-        Json(TimetableDto {
-            trips: trips,
-            message: None,
-        }),
-    ))
+/// Aggregates the cached trips of the given LIOs into a timetable. Nothing is fetched from the
+/// providers here, LIOs without a cached trip are reported as unavailable.
+pub fn build_timetable(app_state: &AppState, lios: &[IntervalLio]) -> TimetableDto {
+    let mut trips: Vec<TripDto> = Vec::new();
+    let mut degraded: Vec<String> = Vec::new();
+    for provider in app_state.providers.iter() {
+        let provider_lios = lios
+            .iter()
+            .filter(|lio| lio.provider == provider.name())
+            .collect::<Vec<&IntervalLio>>();
+
+        if provider_lios.is_empty() {
+            continue;
+        }
+
+        let cached = app_state.trip_cache.trips(&provider_lios);
+        if cached.len() < provider_lios.len() || cached.iter().any(|t| t.stale) {
+            degraded.push(degraded_provider_message(
+                provider.name(),
+                &cached,
+                provider_lios.len(),
+            ));
+        }
+        trips.extend(cached);
+    }
+
+    trips.sort_by_key(|t| t.departures.first().map_or(i32::MAX, |d| d.countdown));
+
+    // Several LIOs usually share a line or station and thereby the same alerts.
+    let mut alerts: Vec<AlertDto> = Vec::new();
+    for alert in trips.iter().flat_map(|t| t.alerts.iter()) {
+        if !alerts.iter().any(|a| a.id == alert.id) {
+            alerts.push(alert.clone());
+        }
+    }
+
+    TimetableDto {
+        trips,
+        message: (!degraded.is_empty()).then(|| degraded.join(" ")),
+        alerts,
+    }
+}
+
+pub async fn select_interval_lios(pool: &MySqlPool) -> Result<Vec<IntervalLio>, sqlx::Error> {
+    sqlx::query_as::<_, IntervalLio>(
+        r#"
+        SELECT id, provider, provider_id, line, direction, foot_minutes_to_station,
+            foot_minutes_estimated, station_latitude, station_longitude
+        FROM lios
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+fn degraded_provider_message(provider: &str, stale: &[TripDto], lio_count: usize) -> String {
+    let Some(oldest) = stale.iter().filter_map(|t| t.age_seconds).max() else {
+        return format!(
+            "{} is currently unreachable, no departures available.",
+            provider
+        );
+    };
+
+    let mut message = format!(
+        "{} is currently unreachable, showing departures from {} minutes ago.",
+        provider,
+        oldest / 60
+    );
+    if stale.len() < lio_count {
+        message.push_str(&format!(
+            " No departures available for {} of {} entries.",
+            lio_count - stale.len(),
+            lio_count
+        ));
+    }
+
+    message
 }
@@ -1,96 +1,285 @@
+use std::{collections::HashMap, time::Duration};
+
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use reqwest::Client;
+use chrono_tz::Europe::Vienna;
+use futures_util::{StreamExt, stream};
+use reqwest::StatusCode;
 
 use crate::{
     dtos::internal::{DepartureDto, TripDto},
     models::{
-        internal::{IntervalLio, Station},
+        internal::{GeoPoint, IntervalLio, Station, StationLine},
         oebb::{Departure, Departures, Location},
     },
+    services::{
+        capture::UpstreamClient,
+        clock::Clock,
+        provider::{ProviderError, TransitProvider, TripResult},
+    },
 };
 
-pub async fn fetch_stations(name: String) -> Result<Vec<Station>, reqwest::Error> {
-    let resp = Client::new()
-        .get(format!(
-            "https://oebb.macistry.com/api/locations?query={}",
-            name
-        ))
-        .send()
+pub const PROVIDER_NAME: &str = "OEBB";
+
+pub struct Oebb {
+    client: UpstreamClient,
+    base_url: String,
+    clock: Clock,
+    max_concurrent_requests: usize,
+    request_deadline: Duration,
+}
+
+impl Oebb {
+    pub fn new(
+        client: UpstreamClient,
+        base_url: String,
+        clock: Clock,
+        max_concurrent_requests: usize,
+        request_deadline: Duration,
+    ) -> Self {
+        Oebb {
+            client,
+            base_url,
+            clock,
+            max_concurrent_requests,
+            request_deadline,
+        }
+    }
+}
+
+#[async_trait]
+impl TransitProvider for Oebb {
+    fn name(&self) -> &str {
+        PROVIDER_NAME
+    }
+
+    async fn resolve_stations(&self, query: &str) -> Result<Vec<Station>, ProviderError> {
+        fetch_stations(&self.client, &self.base_url, query).await
+    }
+
+    async fn station_candidates(&self, query: &str) -> Result<Vec<Station>, ProviderError> {
+        self.resolve_stations(query).await
+    }
+
+    async fn find_station(&self, station_id: &str) -> Result<Option<Station>, ProviderError> {
+        fetch_station(&self.client, &self.base_url, station_id).await
+    }
+
+    async fn stations_nearby(
+        &self,
+        location: GeoPoint,
+        radius_meters: f64,
+    ) -> Result<Vec<Station>, ProviderError> {
+        fetch_stations_nearby(&self.client, &self.base_url, location, radius_meters).await
+    }
+
+    async fn lines_at_station(&self, station_id: &str) -> Result<Vec<StationLine>, ProviderError> {
+        let departures = fetch_departures(&self.client, &self.base_url, station_id).await?;
+
+        let mut lines = departures
+            .iter()
+            .map(|d| StationLine {
+                line: d.line.name.clone(),
+                direction: d.direction.clone(),
+            })
+            .collect::<Vec<StationLine>>();
+        lines.sort();
+        lines.dedup();
+
+        Ok(lines)
+    }
+
+    async fn validate_line(
+        &self,
+        station_id: &str,
+        line: &str,
+        direction: &str,
+    ) -> Result<bool, ProviderError> {
+        Ok(self.lines_at_station(station_id).await?.iter().any(|l| {
+            l.direction
+                .to_lowercase()
+                .contains(&direction.to_lowercase())
+                && l.line
+                    .replace(" ", "")
+                    .to_lowercase()
+                    .contains(&line.to_lowercase())
+        }))
+    }
+
+    async fn fetch_trips_for_lios(
+        &self,
+        lios: &[&IntervalLio],
+    ) -> Result<Vec<TripResult>, ProviderError> {
+        fetch_trips_for_lios(
+            &self.client,
+            &self.base_url,
+            lios,
+            self.max_concurrent_requests,
+            self.request_deadline,
+            self.clock.now(),
+        )
+        .await
+    }
+}
+
+pub async fn fetch_stations(
+    client: &UpstreamClient,
+    base_url: &str,
+    name: &str,
+) -> Result<Vec<Station>, ProviderError> {
+    let resp = client
+        .get_with_query(&format!("{}/locations", base_url), &[("query", name)])
         .await?
-        .json::<Vec<Location>>()
-        .await?;
+        .json::<Vec<Location>>()?;
 
-    Ok(resp
-        .iter()
+    Ok(locations_to_stations(resp))
+}
+
+pub async fn fetch_station(
+    client: &UpstreamClient,
+    base_url: &str,
+    id: &str,
+) -> Result<Option<Station>, ProviderError> {
+    let resp = client.get(&format!("{}/stops/{}", base_url, id)).await?;
+
+    if resp.status == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    let location = resp.error_for_status()?.json::<Location>()?;
+
+    Ok(Some(location_to_station(location)))
+}
+
+pub async fn fetch_stations_nearby(
+    client: &UpstreamClient,
+    base_url: &str,
+    location: GeoPoint,
+    radius_meters: f64,
+) -> Result<Vec<Station>, ProviderError> {
+    let resp = client
+        .get_with_query(
+            &format!("{}/locations/nearby", base_url),
+            &[
+                ("latitude", location.latitude.to_string()),
+                ("longitude", location.longitude.to_string()),
+                ("distance", radius_meters.round().to_string()),
+            ],
+        )
+        .await?
+        .json::<Vec<Location>>()?;
+
+    Ok(locations_to_stations(resp))
+}
+
+fn locations_to_stations(locations: Vec<Location>) -> Vec<Station> {
+    locations
+        .into_iter()
         .filter(|l| l.kind == "stop")
-        .map(|l| Station {
-            id: l.id.to_string(),
-            provider: "OEBB".to_string(),
-            name: l.name.to_string(),
-        })
-        .collect::<Vec<Station>>())
+        .map(location_to_station)
+        .collect::<Vec<Station>>()
+}
+
+fn location_to_station(location: Location) -> Station {
+    Station {
+        id: location.id,
+        provider: PROVIDER_NAME.to_string(),
+        name: location.name,
+        location: location.location.map(|c| GeoPoint {
+            latitude: c.latitude,
+            longitude: c.longitude,
+        }),
+    }
 }
 
 pub async fn fetch_trips_for_lios(
-    lios: &Vec<&IntervalLio>,
-) -> Result<Vec<TripDto>, Box<dyn std::error::Error>> {
+    client: &UpstreamClient,
+    base_url: &str,
+    lios: &[&IntervalLio],
+    max_concurrent_requests: usize,
+    request_deadline: Duration,
+    now: DateTime<Utc>,
+) -> Result<Vec<TripResult>, ProviderError> {
     let ids = lios
         .iter()
         .map(|l| l.provider_id.clone())
         .collect::<Vec<String>>();
 
-    let mut departures: Vec<Departure> = Vec::new();
-    for id in ids {
-        Client::new()
-            .get(format!(
-                "https://oebb.macistry.com/api/stops/{}/departures",
-                id
-            ))
-            .send()
-            .await?
-            .json::<Departures>()
-            .await?
-            .departures
-            .iter()
-            .for_each(|d| {
-                departures.push(d.clone());
-            });
+    let departures = fetch_depatures_for_stations(
+        client,
+        base_url,
+        ids,
+        max_concurrent_requests,
+        request_deadline,
+    )
+    .await;
+
+    // Only a provider that fails for every stop counts as unreachable.
+    if let Some(Err(e)) = departures.values().next()
+        && departures.values().all(Result::is_err)
+    {
+        return Err(e.clone().into());
     }
 
     Ok(lios
         .iter()
-        .map(|lio| (*lio, find_departures_matching_lio(&departures, lio)))
-        .map(|pair| lio_departures_pair_to_trip_dto(&pair))
-        .collect::<Vec<TripDto>>())
+        .map(|lio| match &departures[&lio.provider_id] {
+            Ok(station_departures) => Ok(lio_departures_pair_to_trip_dto(
+                &(*lio, find_departures_matching_lio(station_departures, lio)),
+                now,
+            )),
+            Err(e) => Err(e.clone().into()),
+        })
+        .collect::<Vec<TripResult>>())
 }
 
+/// Fetches the departures of every distinct stop, at most `max_concurrent_requests` at a time.
+/// A stop whose request fails or takes longer than `request_deadline` maps to the error, so one
+/// stop cannot fail the others.
 pub async fn fetch_depatures_for_stations(
-    ids: Vec<String>,
-) -> Result<Vec<Departure>, reqwest::Error> {
-    let mut departures: Vec<Departure> = Vec::new();
-
-    for ele in ids {
-        Client::new()
-            .get(format!(
-                "https://oebb.macistry.com/api/stops/{}/departures",
-                ele
-            ))
-            .send()
-            .await?
-            .json::<Departures>()
-            .await?
-            .departures
-            .iter()
-            .for_each(|d| {
-                departures.push(d.clone());
-            });
-    }
+    client: &UpstreamClient,
+    base_url: &str,
+    mut ids: Vec<String>,
+    max_concurrent_requests: usize,
+    request_deadline: Duration,
+) -> HashMap<String, Result<Vec<Departure>, String>> {
+    // Several LIOs usually share a stop.
+    ids.sort();
+    ids.dedup();
 
-    Ok(departures)
+    stream::iter(ids)
+        .map(|id| async move {
+            let departures =
+                tokio::time::timeout(request_deadline, fetch_departures(client, base_url, &id))
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(
+                            format!("No response within {} seconds", request_deadline.as_secs())
+                                .into(),
+                        )
+                    })
+                    .map_err(|e| format!("Error fetching departures of stop {}: {}", id, e));
+            (id, departures)
+        })
+        .buffer_unordered(max_concurrent_requests.max(1))
+        .collect()
+        .await
+}
+
+pub async fn fetch_departures(
+    client: &UpstreamClient,
+    base_url: &str,
+    id: &str,
+) -> Result<Vec<Departure>, ProviderError> {
+    Ok(client
+        .get(&format!("{}/stops/{}/departures", base_url, id))
+        .await?
+        .json::<Departures>()?
+        .departures)
 }
 
 fn find_departures_matching_lio<'a>(
-    departures: &'a Vec<Departure>,
+    departures: &'a [Departure],
     lio: &'a IntervalLio,
 ) -> Vec<&'a Departure> {
     departures
@@ -110,68 +299,85 @@ fn find_departures_matching_lio<'a>(
         .collect::<Vec<&Departure>>()
 }
 
-fn lio_departures_pair_to_trip_dto(pair: &(&IntervalLio, Vec<&Departure>)) -> TripDto {
+fn lio_departures_pair_to_trip_dto(
+    pair: &(&IntervalLio, Vec<&Departure>),
+    now: DateTime<Utc>,
+) -> TripDto {
     let (lio, departures) = pair;
 
     TripDto {
         line: lio.line.clone(),
         direction: lio.direction.clone(),
-        foot_minutes_to_station: 5,
+        foot_minutes_to_station: lio.foot_minutes_to_station,
         departures: departures
             .iter()
-            .map(|d| departure_to_departure_dto(d))
+            .filter_map(|d| departure_to_departure_dto(d, lio.foot_minutes_to_station, now))
             .collect::<Vec<DepartureDto>>(),
+        stale: false,
+        age_seconds: None,
+        alerts: vec![],
     }
 }
 
-fn departure_to_departure_dto(departure: &Departure) -> DepartureDto {
-    let calc_countdown = |when: DateTime<Utc>| when.signed_duration_since(Utc::now()).num_minutes();
+fn departure_to_departure_dto(
+    departure: &Departure,
+    foot_minutes_to_station: i32,
+    now: DateTime<Utc>,
+) -> Option<DepartureDto> {
+    let calc_countdown = |when: DateTime<Utc>| when.signed_duration_since(now).num_minutes();
+    let parse_time = |time: &str| {
+        DateTime::parse_from_rfc3339(time)
+            .ok()
+            .map(|t| t.with_timezone(&Utc))
+    };
 
-    let planned_when_date_time = DateTime::parse_from_rfc3339(departure.planned_when.as_str())
-        .unwrap()
-        .with_timezone(&Utc);
+    // Without a valid planned time there is nothing to count down from, a malformed realtime
+    // time is treated like a missing one.
+    let Some(planned_when_date_time) = parse_time(&departure.planned_when) else {
+        tracing::warn!(
+            "Skipping departure with invalid planned time '{}'",
+            departure.planned_when
+        );
+        return None;
+    };
+    let when_date_time = departure.when.as_deref().and_then(parse_time);
 
-    let (countdown, real_time, late) = departure.when.clone().map_or_else(
+    let (countdown, real_time, late) = when_date_time.map_or_else(
         || (calc_countdown(planned_when_date_time), false, false),
-        |w| {
-            let when_date_time = DateTime::parse_from_rfc3339(w.as_str())
-                .unwrap()
-                .with_timezone(&Utc);
-            (
-                calc_countdown(when_date_time),
-                true,
-                when_date_time > planned_when_date_time,
-            )
-        },
+        |w| (calc_countdown(w), true, w > planned_when_date_time),
     );
 
-    DepartureDto {
+    let platform_changed = matches!(
+        (&departure.platform, &departure.planned_platform),
+        (Some(platform), Some(planned_platform)) if platform != planned_platform
+    );
+
+    Some(DepartureDto {
         direction: Some(departure.direction.clone()),
+        planned_time: Some(planned_when_date_time.with_timezone(&Vienna).fixed_offset()),
+        expected_time: when_date_time.map(|w| w.with_timezone(&Vienna).fixed_offset()),
         countdown: countdown as i32,
-        real_time: real_time,
-        late: late,
+        leave_in: countdown as i32 - foot_minutes_to_station,
+        real_time,
+        late,
         traffic_jam: false,
-    }
-}
-
-pub fn filter_departures_for_lios(
-    departures: &Vec<Departure>,
-    lios: &Vec<&IntervalLio>,
-) -> Vec<Departure> {
-    departures
-        .iter()
-        .filter(|d| {
-            lios.iter().any(|l| {
-                d.line
-                    .name
-                    .replace(" ", "")
-                    .to_lowercase()
-                    .contains(&l.line.to_lowercase())
-                    && d.direction
-                        .to_lowercase()
-                        .contains(&l.direction.to_lowercase())
-            })
-        })
-        .cloned()
-        .collect::<Vec<Departure>>()
+        cancelled: departure.cancelled,
+        platform: departure
+            .platform
+            .clone()
+            .or(departure.planned_platform.clone()),
+        platform_changed,
+        delay_seconds: departure.delay.or(when_date_time.map(|w| {
+            w.signed_duration_since(planned_when_date_time)
+                .num_seconds() as i32
+        })),
+        // Hints are mostly static facts like bicycle transport, only status messages and
+        // warnings are worth showing on a board.
+        remarks: departure
+            .remarks
+            .iter()
+            .filter(|r| r.kind != "hint")
+            .filter_map(|r| r.text.clone())
+            .collect(),
+    })
 }
@@ -0,0 +1,78 @@
+use std::{collections::HashMap, error::Error, sync::Arc};
+
+use async_trait::async_trait;
+
+use crate::{
+    dtos::internal::TripDto,
+    models::internal::{GeoPoint, IntervalLio, Station, StationLine},
+};
+
+pub type ProviderError = Box<dyn Error + Send + Sync>;
+
+pub type SharedProvider = Arc<dyn TransitProvider>;
+
+/// The trip of a single LIO. Providers that fetch stops separately can fail for some LIOs only.
+pub type TripResult = Result<TripDto, ProviderError>;
+
+/// A transit operator that LIOs can be created for and departures fetched from.
+#[async_trait]
+pub trait TransitProvider: Send + Sync {
+    /// Name of the provider as stored in the `provider` column of `lios`.
+    fn name(&self) -> &str;
+
+    /// Returns all stations of this provider matching the given name.
+    async fn resolve_stations(&self, query: &str) -> Result<Vec<Station>, ProviderError>;
+
+    /// Returns the stations a fuzzy search for `query` should rank. Providers with a local station
+    /// list return all of their stations.
+    async fn station_candidates(&self, query: &str) -> Result<Vec<Station>, ProviderError>;
+
+    /// Looks up a station by its provider id.
+    async fn find_station(&self, station_id: &str) -> Result<Option<Station>, ProviderError>;
+
+    /// Returns the stations within `radius_meters` of `location`, in no particular order.
+    async fn stations_nearby(
+        &self,
+        location: GeoPoint,
+        radius_meters: f64,
+    ) -> Result<Vec<Station>, ProviderError>;
+
+    /// Returns the distinct line/direction pairs currently served at the station with the given
+    /// provider id.
+    async fn lines_at_station(&self, station_id: &str) -> Result<Vec<StationLine>, ProviderError>;
+
+    /// Checks whether a line with the given direction departs from a station.
+    async fn validate_line(
+        &self,
+        station_id: &str,
+        line: &str,
+        direction: &str,
+    ) -> Result<bool, ProviderError>;
+
+    /// Fetches one trip per LIO, in the order of the given LIOs. Fails as a whole if the provider
+    /// cannot be reached at all.
+    async fn fetch_trips_for_lios(
+        &self,
+        lios: &[&IntervalLio],
+    ) -> Result<Vec<TripResult>, ProviderError>;
+}
+
+#[derive(Clone, Default)]
+pub struct ProviderRegistry {
+    providers: HashMap<String, SharedProvider>,
+}
+
+impl ProviderRegistry {
+    pub fn register(&mut self, provider: impl TransitProvider + 'static) {
+        self.providers
+            .insert(provider.name().to_string(), Arc::new(provider));
+    }
+
+    pub fn get(&self, name: &str) -> Option<SharedProvider> {
+        self.providers.get(name).cloned()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SharedProvider> {
+        self.providers.values()
+    }
+}
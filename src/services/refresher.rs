@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use futures_util::future::join_all;
+
+use crate::{
+    AppState,
+    models::internal::IntervalLio,
+    services::{
+        cache::TripCache,
+        internal::select_interval_lios,
+        provider::{ProviderError, TransitProvider},
+    },
+};
+
+/// Polls all providers concurrently for the trips of their LIOs on a fixed interval, so
+/// `/timetable` can be served from the cache no matter how many displays are polling it.
+pub async fn run(app_state: AppState, interval: Duration) {
+    let app_state = &app_state;
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+
+        let lios = match select_interval_lios(&app_state.pool).await {
+            Ok(lios) => lios,
+            Err(e) => {
+                tracing::error!("Error loading LIOs for refresh: {}", e);
+                continue;
+            }
+        };
+
+        refresh_lios(app_state, &lios.iter().collect::<Vec<&IntervalLio>>()).await;
+    }
+}
+
+/// Fetches the trips of the given LIOs from all of their providers concurrently and stores them
+/// in the cache.
+pub async fn refresh_lios(app_state: &AppState, lios: &[&IntervalLio]) {
+    join_all(app_state.providers.iter().filter_map(|provider| {
+        let provider_lios = lios
+            .iter()
+            .filter(|lio| lio.provider == provider.name())
+            .copied()
+            .collect::<Vec<&IntervalLio>>();
+
+        if provider_lios.is_empty() {
+            return None;
+        }
+
+        Some(async move {
+            if let Err(e) =
+                refresh_provider(provider.as_ref(), &provider_lios, &app_state.trip_cache).await
+            {
+                tracing::warn!("Error refreshing trips from {}: {}", provider.name(), e);
+            }
+        })
+    }))
+    .await;
+}
+
+/// Fetches the trips of the given LIOs from a provider and stores them in the cache, or records
+/// the provider as failing. LIOs whose trip alone could not be fetched are recorded as failing.
+async fn refresh_provider(
+    provider: &dyn TransitProvider,
+    lios: &[&IntervalLio],
+    cache: &TripCache,
+) -> Result<(), ProviderError> {
+    match provider.fetch_trips_for_lios(lios).await {
+        Ok(trips) => {
+            for (lio, trip) in lios.iter().zip(&trips) {
+                if let Err(e) = trip {
+                    tracing::warn!("Error refreshing trip of LIO {}: {}", lio.id, e);
+                }
+            }
+            cache.store(provider.name(), lios, &trips);
+            Ok(())
+        }
+        Err(e) => {
+            cache.mark_failed(provider.name());
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::HashSet;
+
+use strsim::levenshtein;
+
+use crate::models::internal::Station;
+
+const SCORE_EXACT: u32 = 100;
+const SCORE_PREFIX: u32 = 80;
+const SCORE_TOKENS: u32 = 60;
+const SCORE_CONTAINS: u32 = 50;
+const SCORE_TYPO: u32 = 30;
+
+/// A station matching a search query. Higher scores are better matches.
+#[derive(Debug, Clone)]
+pub struct RankedStation {
+    pub station: Station,
+    pub score: u32,
+}
+
+/// Ranks stations by how well their name matches the query, best match first. Stations that do
+/// not match at all are left out.
+///
+/// Matches are tried from strictest to loosest: exact name, name prefix, every query word being
+/// the start of a word in the name (so "Schwedenpl." finds "Schwedenplatz"), substring, and
+/// finally every query word being within a small edit distance of a word in the name. Names and
+/// queries are compared case-insensitively with umlauts and "ß" spelled out.
+pub fn rank_stations(query: &str, stations: Vec<Station>) -> Vec<RankedStation> {
+    let query = normalize(query);
+    let query_tokens = query.split(' ').collect::<Vec<&str>>();
+
+    if query.is_empty() {
+        return vec![];
+    }
+
+    let mut seen = HashSet::new();
+    let mut ranked = stations
+        .into_iter()
+        .filter(|s| seen.insert((s.provider.clone(), s.id.clone())))
+        .filter_map(|station| {
+            let score = score(&query, &query_tokens, &normalize(&station.name))?;
+            Some(RankedStation { station, score })
+        })
+        .collect::<Vec<RankedStation>>();
+
+    ranked.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(a.station.name.len().cmp(&b.station.name.len()))
+            .then(a.station.name.cmp(&b.station.name))
+    });
+
+    ranked
+}
+
+fn score(query: &str, query_tokens: &[&str], name: &str) -> Option<u32> {
+    if name == query {
+        return Some(SCORE_EXACT);
+    }
+    if name.starts_with(query) {
+        return Some(SCORE_PREFIX);
+    }
+
+    let name_tokens = name.split(' ').collect::<Vec<&str>>();
+
+    if query_tokens
+        .iter()
+        .all(|q| name_tokens.iter().any(|n| n.starts_with(q)))
+    {
+        return Some(SCORE_TOKENS);
+    }
+    if name.contains(query) {
+        return Some(SCORE_CONTAINS);
+    }
+
+    let mut total_distance = 0;
+    for q in query_tokens {
+        let allowed = max_typos(q);
+        let distance = name_tokens
+            .iter()
+            .map(|n| typo_distance(q, n))
+            .min()
+            .filter(|d| *d <= allowed)?;
+        total_distance += distance as u32;
+    }
+
+    Some(SCORE_TYPO.saturating_sub(total_distance))
+}
+
+/// Edit distance between a query word and a name word, also allowing the query word to be a
+/// misspelled abbreviation of the name word.
+fn typo_distance(query: &str, name: &str) -> usize {
+    let prefix = name.chars().take(query.chars().count()).collect::<String>();
+    levenshtein(query, name).min(levenshtein(query, &prefix))
+}
+
+fn max_typos(token: &str) -> usize {
+    match token.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Lowercases, spells out umlauts and "ß" and turns punctuation into single spaces.
+pub fn normalize(s: &str) -> String {
+    let mut normalized = String::with_capacity(s.len());
+    for c in s.to_lowercase().chars() {
+        match c {
+            'ä' => normalized.push_str("ae"),
+            'ö' => normalized.push_str("oe"),
+            'ü' => normalized.push_str("ue"),
+            'ß' => normalized.push_str("ss"),
+            'á' | 'à' | 'â' => normalized.push('a'),
+            'é' | 'è' | 'ê' => normalized.push('e'),
+            'í' | 'ì' | 'î' => normalized.push('i'),
+            'ó' | 'ò' | 'ô' => normalized.push('o'),
+            'ú' | 'ù' | 'û' => normalized.push('u'),
+            c if c.is_alphanumeric() => normalized.push(c),
+            _ => normalized.push(' '),
+        }
+    }
+
+    normalized
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn station(id: &str, name: &str) -> Station {
+        Station {
+            id: id.to_string(),
+            name: name.to_string(),
+            provider: "Wiener Linien".to_string(),
+            location: None,
+        }
+    }
+
+    fn ranked(query: &str, names: &[&str]) -> Vec<(String, u32)> {
+        let stations = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| station(&i.to_string(), name))
+            .collect();
+
+        rank_stations(query, stations)
+            .into_iter()
+            .map(|r| (r.station.name, r.score))
+            .collect()
+    }
+
+    #[test]
+    fn normalize_folds_case_umlauts_and_punctuation() {
+        assert_eq!(normalize("Schwedenpl."), "schwedenpl");
+        assert_eq!(normalize("Gumpendorfer Straße"), "gumpendorfer strasse");
+        assert_eq!(normalize("Westbahnhof/Gürtel"), "westbahnhof guertel");
+        assert_eq!(
+            normalize("  Wien   Mitte-Landstraße "),
+            "wien mitte landstrasse"
+        );
+        assert_eq!(normalize("Hütteldorf Bahnhof É"), "huetteldorf bahnhof e");
+    }
+
+    #[test]
+    fn exact_match_ranks_before_prefix_match() {
+        assert_eq!(
+            ranked("karlsplatz", &["Karlsplatz U", "Karlsplatz"]),
+            [
+                ("Karlsplatz".to_string(), SCORE_EXACT),
+                ("Karlsplatz U".to_string(), SCORE_PREFIX),
+            ]
+        );
+    }
+
+    #[test]
+    fn prefix_matches_shorter_names_first() {
+        assert_eq!(
+            ranked("Karls", &["Karlsplatz U", "Karlsplatz", "Stephansplatz"]),
+            [
+                ("Karlsplatz".to_string(), SCORE_PREFIX),
+                ("Karlsplatz U".to_string(), SCORE_PREFIX),
+            ]
+        );
+    }
+
+    #[test]
+    fn abbreviations_match_word_starts() {
+        assert_eq!(
+            ranked("Schwedenpl.", &["Schwedenplatz", "Schottenring"]),
+            [("Schwedenplatz".to_string(), SCORE_PREFIX)]
+        );
+        assert_eq!(
+            ranked("W. Mitte", &["Wien Mitte", "Mitte"]),
+            [("Wien Mitte".to_string(), SCORE_TOKENS)]
+        );
+    }
+
+    #[test]
+    fn substring_matches_inside_words() {
+        assert_eq!(
+            ranked("platz", &["Karlsplatz"]),
+            [("Karlsplatz".to_string(), SCORE_CONTAINS)]
+        );
+    }
+
+    #[test]
+    fn umlauts_and_sharp_s_match_their_spelled_out_form() {
+        assert_eq!(
+            ranked("Gumpendorfer Strasse", &["Gumpendorfer Straße"]),
+            [("Gumpendorfer Straße".to_string(), SCORE_EXACT)]
+        );
+        assert_eq!(
+            ranked("Hütteldorf", &["Huetteldorf"]),
+            [("Huetteldorf".to_string(), SCORE_EXACT)]
+        );
+        // A plain "o" for "ö" is one edit away from "oe".
+        assert_eq!(
+            ranked("Schonbrunn", &["Schönbrunn"]),
+            [("Schönbrunn".to_string(), SCORE_TYPO - 1)]
+        );
+    }
+
+    #[test]
+    fn typos_are_tolerated_depending_on_word_length() {
+        assert_eq!(
+            ranked("Stephnsplatz", &["Stephansplatz"]),
+            [("Stephansplatz".to_string(), SCORE_TYPO - 1)]
+        );
+        assert_eq!(
+            ranked("Karlspaltz", &["Karlsplatz"]),
+            [("Karlsplatz".to_string(), SCORE_TYPO - 2)]
+        );
+        // Short words have to match exactly.
+        assert_eq!(ranked("Ulm", &["Ula"]), []);
+        assert_eq!(ranked("Stefnsplaz", &["Stephansplatz"]), []);
+    }
+
+    #[test]
+    fn misspelled_abbreviations_are_tolerated() {
+        assert_eq!(
+            ranked("Schwednpl", &["Schwedenplatz"]),
+            [("Schwedenplatz".to_string(), SCORE_TYPO - 2)]
+        );
+    }
+
+    #[test]
+    fn duplicates_and_empty_queries_are_dropped() {
+        let stations = vec![station("1", "Karlsplatz"), station("1", "Karlsplatz")];
+        assert_eq!(rank_stations("Karlsplatz", stations.clone()).len(), 1);
+        assert!(rank_stations(" . ", stations).is_empty());
+    }
+}
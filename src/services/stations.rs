@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use reqwest::StatusCode;
+
+use crate::{
+    AppState,
+    dtos::internal::{
+        ErrorDto, NearbyStationDto, NearbyStationsQuery, StationLineDto, StationSearchDto,
+        StationSearchQuery,
+    },
+    models::internal::{GeoPoint, Station},
+    services::{
+        geo::{distance_meters, is_valid},
+        provider::SharedProvider,
+        search::rank_stations,
+    },
+};
+
+/// Number of search results returned when the request does not specify a limit.
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// Radius of the nearby search when the request does not specify one, in meters.
+const DEFAULT_NEARBY_RADIUS_METERS: f64 = 500.0;
+
+/// Largest accepted radius of the nearby search, in meters.
+const MAX_NEARBY_RADIUS_METERS: f64 = 5000.0;
+
+pub async fn search_stations(
+    State(app_state): State<AppState>,
+    Query(query): Query<StationSearchQuery>,
+) -> Result<Json<Vec<StationSearchDto>>, (StatusCode, Json<ErrorDto>)> {
+    let providers = select_providers(&app_state, query.provider.as_deref())?;
+
+    let mut candidates: Vec<Station> = Vec::new();
+    for provider in providers {
+        match provider.station_candidates(&query.q).await {
+            Ok(stations) => candidates.extend(stations),
+            // A single unreachable provider should not break searching the others.
+            Err(e) if query.provider.is_none() => {
+                tracing::warn!("Error searching stations of {}: {}", provider.name(), e);
+            }
+            Err(e) => {
+                tracing::error!("Error searching stations of {}: {}", provider.name(), e);
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorDto {
+                        message: format!("Failed to fetch stations from {} API.", provider.name()),
+                    }),
+                ));
+            }
+        }
+    }
+
+    Ok(Json(
+        rank_stations(&query.q, candidates)
+            .into_iter()
+            .take(query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+            .map(|r| StationSearchDto {
+                provider: r.station.provider,
+                id: r.station.id,
+                name: r.station.name,
+                score: r.score,
+            })
+            .collect(),
+    ))
+}
+
+pub async fn get_nearby_stations(
+    State(app_state): State<AppState>,
+    Query(query): Query<NearbyStationsQuery>,
+) -> Result<Json<Vec<NearbyStationDto>>, (StatusCode, Json<ErrorDto>)> {
+    let location = GeoPoint {
+        latitude: query.lat,
+        longitude: query.lon,
+    };
+    let radius = query.radius.unwrap_or(DEFAULT_NEARBY_RADIUS_METERS);
+    if !is_valid(location) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorDto {
+                message: "Coordinates out of range.".to_string(),
+            }),
+        ));
+    }
+    if !(radius > 0.0 && radius <= MAX_NEARBY_RADIUS_METERS) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorDto {
+                message: format!(
+                    "Radius must be between 0 and {} meters.",
+                    MAX_NEARBY_RADIUS_METERS
+                ),
+            }),
+        ));
+    }
+
+    let providers = select_providers(&app_state, query.provider.as_deref())?;
+
+    let mut seen = HashSet::new();
+    let mut nearby: Vec<(Station, GeoPoint, f64)> = Vec::new();
+    for provider in providers {
+        let stations = match provider.stations_nearby(location, radius).await {
+            Ok(stations) => stations,
+            Err(e) if query.provider.is_none() => {
+                tracing::warn!(
+                    "Error fetching stations near {:?} from {}: {}",
+                    location,
+                    provider.name(),
+                    e
+                );
+                continue;
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Error fetching stations near {:?} from {}: {}",
+                    location,
+                    provider.name(),
+                    e
+                );
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorDto {
+                        message: format!("Failed to fetch stations from {} API.", provider.name()),
+                    }),
+                ));
+            }
+        };
+
+        for station in stations {
+            let Some(station_location) = station.location else {
+                continue;
+            };
+            let distance = distance_meters(location, station_location);
+            if distance <= radius && seen.insert((station.provider.clone(), station.id.clone())) {
+                nearby.push((station, station_location, distance));
+            }
+        }
+    }
+
+    nearby.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    Ok(Json(
+        nearby
+            .into_iter()
+            .take(query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+            .map(|(station, station_location, distance)| NearbyStationDto {
+                provider: station.provider,
+                id: station.id,
+                name: station.name,
+                lat: station_location.latitude,
+                lon: station_location.longitude,
+                distance_meters: distance.round() as u32,
+            })
+            .collect(),
+    ))
+}
+
+pub async fn get_station_lines(
+    State(app_state): State<AppState>,
+    Path((provider_name, id)): Path<(String, String)>,
+) -> Result<Json<Vec<StationLineDto>>, (StatusCode, Json<ErrorDto>)> {
+    let provider = app_state.providers.get(&provider_name).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorDto {
+                message: format!("Provider '{}' not supported", provider_name),
+            }),
+        )
+    })?;
+
+    let lines = provider.lines_at_station(&id).await.map_err(|e| {
+        tracing::error!("Error fetching lines: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorDto {
+                message: format!("Failed to fetch departures from {} API.", provider.name()),
+            }),
+        )
+    })?;
+
+    Ok(Json(
+        lines
+            .into_iter()
+            .map(|l| StationLineDto {
+                line: l.line,
+                direction: l.direction,
+            })
+            .collect(),
+    ))
+}
+
+/// Returns the provider with the given name, or all providers if no name is given.
+fn select_providers(
+    app_state: &AppState,
+    name: Option<&str>,
+) -> Result<Vec<SharedProvider>, (StatusCode, Json<ErrorDto>)> {
+    match name {
+        Some(name) => Ok(vec![app_state.providers.get(name).ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorDto {
+                    message: format!("Provider '{}' not supported", name),
+                }),
+            )
+        })?]),
+        None => Ok(app_state.providers.iter().cloned().collect()),
+    }
+}
@@ -0,0 +1,22 @@
+use axum::{Json, extract::State};
+use chrono::Utc;
+
+use crate::{
+    AppState,
+    dtos::internal::{StationListStatusDto, StatusDto},
+    services::wl,
+};
+
+pub async fn get_status(State(app_state): State<AppState>) -> Json<StatusDto> {
+    let fetched_at = app_state.wl_stations.fetched_at();
+
+    Json(StatusDto {
+        stations: StationListStatusDto {
+            provider: wl::PROVIDER_NAME.to_string(),
+            station_count: app_state.wl_stations.stations().len(),
+            fetched_at,
+            age_seconds: fetched_at.map(|f| Utc::now().signed_duration_since(f).num_seconds()),
+            refresh_error: app_state.wl_stations.refresh_error(),
+        },
+    })
+}
@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::Stream;
+use tokio::time::{Instant, interval_at};
+
+use crate::{
+    AppState,
+    dtos::internal::TimetableDto,
+    services::internal::{build_timetable, select_interval_lios},
+};
+
+/// How often the timetable is sent even if nothing changed, so countdowns keep ticking.
+const TICK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Reconnection delay suggested to clients.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Streams the timetable as server-sent events. A new event is sent whenever the cached trips
+/// change and at least once per `TICK_INTERVAL`. Timetables are built from the cache only, so
+/// connected clients never cause requests to the providers. Event ids continue from
+/// `Last-Event-ID` so they stay increasing across reconnects.
+pub async fn stream_timetable(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let mut event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0);
+    let mut updates = app_state.trip_cache.subscribe();
+
+    let stream = async_stream::stream! {
+        let mut ticker = interval_at(Instant::now() + TICK_INTERVAL, TICK_INTERVAL);
+        let mut last_sent: Option<TimetableDto> = None;
+        let mut force = true;
+
+        loop {
+            match select_interval_lios(&app_state.pool).await {
+                Ok(lios) => {
+                    let timetable = build_timetable(&app_state, &lios);
+                    if force || last_sent.as_ref() != Some(&timetable) {
+                        event_id += 1;
+                        yield Event::default()
+                            .event("timetable")
+                            .id(event_id.to_string())
+                            .retry(RETRY_INTERVAL)
+                            .json_data(&timetable);
+                        last_sent = Some(timetable);
+                    }
+                }
+                Err(e) => tracing::error!("Error loading LIOs for stream: {}", e),
+            }
+
+            tokio::select! {
+                _ = ticker.tick() => force = true,
+                changed = updates.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    force = false;
+                }
+            }
+        }
+    };
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
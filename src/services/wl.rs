@@ -1,27 +1,139 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Vienna;
 use csv::ReaderBuilder;
-use reqwest::Client;
 
 use crate::{
-    dtos::internal::{DepartureDto, TripDto},
+    dtos::internal::{AlertDto, AlertKind, DepartureDto, TripDto},
     models::{
-        internal::{IntervalLio, Station},
-        wl::{Departure, Line, Monitor, MonitorResponse, StationCsvRow},
+        internal::{GeoPoint, IntervalLio, Station, StationLine, geo_point},
+        wl::{Data, Departure, Monitor, MonitorResponse, StationCsvRow, TrafficInfo},
+    },
+    services::{
+        capture::UpstreamClient,
+        geo::stations_within,
+        provider::{ProviderError, TransitProvider, TripResult},
+        wl_stations::StationStore,
     },
 };
 
-pub async fn get_stations() -> Result<Vec<Station>, Box<dyn std::error::Error>> {
-    let resp = Client::new()
-        .get("https://www.wienerlinien.at/ogd_realtime/doku/ogd/wienerlinien-ogd-haltestellen.csv")
-        .send()
-        .await?
-        .text()
-        .await?;
+pub const PROVIDER_NAME: &str = "Wiener Linien";
+
+/// Names that refer to the same line, matched like the line names themselves.
+const LINE_ALIASES: &[&[&str]] = &[&["WLB", "Badner Bahn"], &["VRT", "Vienna Ring Tram"]];
+
+pub struct WienerLinien {
+    stations: StationStore,
+    client: UpstreamClient,
+    base_url: String,
+}
+
+impl WienerLinien {
+    pub fn new(stations: StationStore, client: UpstreamClient, base_url: String) -> Self {
+        WienerLinien {
+            stations,
+            client,
+            base_url,
+        }
+    }
+}
+
+#[async_trait]
+impl TransitProvider for WienerLinien {
+    fn name(&self) -> &str {
+        PROVIDER_NAME
+    }
+
+    async fn resolve_stations(&self, query: &str) -> Result<Vec<Station>, ProviderError> {
+        Ok(self
+            .stations
+            .stations()
+            .iter()
+            .filter(|s| s.name.to_lowercase().contains(&query.to_lowercase()))
+            .cloned()
+            .collect::<Vec<Station>>())
+    }
+
+    async fn station_candidates(&self, _query: &str) -> Result<Vec<Station>, ProviderError> {
+        Ok(self.stations.stations().to_vec())
+    }
+
+    async fn find_station(&self, station_id: &str) -> Result<Option<Station>, ProviderError> {
+        Ok(self
+            .stations
+            .stations()
+            .iter()
+            .find(|s| s.id == station_id)
+            .cloned())
+    }
+
+    async fn stations_nearby(
+        &self,
+        location: GeoPoint,
+        radius_meters: f64,
+    ) -> Result<Vec<Station>, ProviderError> {
+        Ok(stations_within(
+            &self.stations.stations(),
+            location,
+            radius_meters,
+        ))
+    }
+
+    async fn lines_at_station(&self, station_id: &str) -> Result<Vec<StationLine>, ProviderError> {
+        let monitor_response =
+            fetch_monitors(&self.client, &self.base_url, vec![station_id.to_string()]).await?;
+
+        let mut lines = monitor_response
+            .data
+            .monitors
+            .iter()
+            .flat_map(|m| m.lines.iter())
+            .map(|l| StationLine {
+                line: l.name.trim().to_string(),
+                direction: l.towards.trim().to_string(),
+            })
+            .collect::<Vec<StationLine>>();
+        lines.sort();
+        lines.dedup();
+
+        Ok(lines)
+    }
+
+    async fn validate_line(
+        &self,
+        station_id: &str,
+        line: &str,
+        direction: &str,
+    ) -> Result<bool, ProviderError> {
+        Ok(self
+            .lines_at_station(station_id)
+            .await?
+            .iter()
+            .any(|l| line_matches(&l.line, line) && direction_matches(&l.direction, direction)))
+    }
+
+    async fn fetch_trips_for_lios(
+        &self,
+        lios: &[&IntervalLio],
+    ) -> Result<Vec<TripResult>, ProviderError> {
+        // All stations are fetched with a single request, which either fails or not.
+        let trips = fetch_trips_for_lios(&self.client, &self.base_url, lios).await?;
+        Ok(trips.into_iter().map(Ok).collect())
+    }
+}
+
+pub async fn get_stations(
+    client: &UpstreamClient,
+    url: &str,
+) -> Result<Vec<Station>, ProviderError> {
+    let resp = client.get(url).await?.error_for_status()?;
 
     let mut rdr = ReaderBuilder::new()
         .has_headers(true)
         .delimiter(b';')
-        .from_reader(resp.as_bytes());
+        .from_reader(resp.body.as_bytes());
 
     let mut rows = Vec::new();
     for result in rdr.deserialize() {
@@ -29,191 +141,215 @@ pub async fn get_stations() -> Result<Vec<Station>, Box<dyn std::error::Error>>
         rows.push(Station {
             id: row.diva,
             name: row.platform_text,
-            provider: "Wiener Linien".to_string(),
+            provider: PROVIDER_NAME.to_string(),
+            location: geo_point(row.latitude, row.longitude),
         });
     }
 
     Ok(rows)
 }
 
-pub async fn fetch_monitors(divas: Vec<String>) -> Result<MonitorResponse, reqwest::Error> {
+pub async fn fetch_monitors(
+    client: &UpstreamClient,
+    base_url: &str,
+    divas: Vec<String>,
+) -> Result<MonitorResponse, ProviderError> {
     let divas_param = divas.join(",");
     let url = format!(
-        "https://www.wienerlinien.at/ogd_realtime/monitor?diva={}",
-        divas_param
+        "{}/monitor?diva={}&activateTrafficInfo=stoerunglang&activateTrafficInfo=aufzugsinfo",
+        base_url, divas_param
     );
 
-    let resp = Client::new()
-        .get(url)
-        .send()
-        .await?
-        .json::<MonitorResponse>()
-        .await?;
-
-    Ok(resp)
+    client.get(&url).await?.json::<MonitorResponse>()
 }
 
 pub async fn fetch_trips_for_lios(
-    lios: &Vec<&IntervalLio>,
-) -> Result<Vec<TripDto>, Box<dyn std::error::Error>> {
-    let divas = lios
+    client: &UpstreamClient,
+    base_url: &str,
+    lios: &[&IntervalLio],
+) -> Result<Vec<TripDto>, ProviderError> {
+    let mut divas = lios
         .iter()
         .map(|l| l.provider_id.clone())
-        .collect::<Vec<String>>()
-        .join(",");
+        .collect::<Vec<String>>();
+    divas.sort();
+    divas.dedup();
 
-    let url = format!(
-        "https://www.wienerlinien.at/ogd_realtime/monitor?diva={}",
-        divas
-    );
+    let monitor_response = fetch_monitors(client, base_url, divas).await?;
 
-    let monitor_response = Client::new()
-        .get(url)
-        .send()
-        .await?
-        .json::<MonitorResponse>()
-        .await?;
+    Ok(lios
+        .iter()
+        .map(|lio| {
+            let departures = find_departures_matching_lio(&monitor_response.data.monitors, lio);
+            let mut trip = lio_departures_pair_to_trip_dto(&(*lio, departures));
+            trip.alerts = alerts_for_lio(&monitor_response.data, lio);
+            trip
+        })
+        .collect::<Vec<TripDto>>())
+}
 
-    let lines = monitor_response
-        .data
+/// Returns the disruptions of the LIO's line and the elevator outages at its station.
+fn alerts_for_lio(data: &Data, lio: &IntervalLio) -> Vec<AlertDto> {
+    let station_rbls = data
         .monitors
         .iter()
-        .filter_map(|m| m.lines.first())
-        .collect::<Vec<&Line>>();
+        .filter(|m| m.location_stop.properties.name == lio.provider_id)
+        .filter_map(|m| m.location_stop.properties.attributes.rbl)
+        .collect::<HashSet<i64>>();
 
-    Ok(lios
+    data.traffic_infos
         .iter()
-        .map(|lio| (*lio, find_line_matching_lio(&lines, lio)))
-        .map(|pair| lio_line_pair_to_trip_dto(&pair))
-        .collect::<Vec<TripDto>>())
+        .filter_map(|info| {
+            let category = data
+                .traffic_info_categories
+                .iter()
+                .find(|c| c.id == info.category_id)?;
+
+            let (kind, relevant) = match category.name.as_str() {
+                "stoerunglang" => (
+                    AlertKind::Disruption,
+                    info.related_lines
+                        .iter()
+                        .any(|l| line_matches(l, &lio.line)),
+                ),
+                "aufzugsinfo" => (
+                    AlertKind::ElevatorOutage,
+                    info.related_stops.iter().any(|s| station_rbls.contains(s)),
+                ),
+                _ => return None,
+            };
+
+            relevant.then(|| traffic_info_to_alert_dto(info, kind))
+        })
+        .collect()
 }
 
-fn find_line_matching_lio<'a>(lines: &'a Vec<&Line>, lio: &'a IntervalLio) -> Option<&'a Line> {
-    lines
+fn traffic_info_to_alert_dto(info: &TrafficInfo, kind: AlertKind) -> AlertDto {
+    let time = info.time.as_ref();
+
+    AlertDto {
+        id: info.name.clone(),
+        kind,
+        title: info.title.trim().to_string(),
+        description: info.description.as_ref().map(|d| d.trim().to_string()),
+        lines: info.related_lines.clone(),
+        valid_from: time
+            .and_then(|t| t.start.as_ref())
+            .and_then(|s| s.parse::<DateTime<Utc>>().ok()),
+        valid_until: time
+            .and_then(|t| t.end.as_ref())
+            .and_then(|s| s.parse::<DateTime<Utc>>().ok()),
+    }
+}
+
+/// Collects the departures of the LIO's line and direction from every line of every monitor at
+/// its DIVA. A DIVA has a monitor per platform and a monitor can carry several lines, so the
+/// departures of one LIO may be spread over several of them.
+fn find_departures_matching_lio<'a>(
+    monitors: &'a [Monitor],
+    lio: &IntervalLio,
+) -> Vec<&'a Departure> {
+    monitors
         .iter()
-        .filter(|line| {
-            line.name
-                .trim()
-                .to_lowercase()
-                .contains(&lio.line.to_lowercase())
-                && line
-                    .towards
-                    .trim()
-                    .to_lowercase()
-                    .contains(&lio.direction.to_lowercase())
+        .filter(|m| m.location_stop.properties.name == lio.provider_id)
+        .flat_map(|m| m.lines.iter())
+        .flat_map(|line| line.departures.departure.iter().map(move |d| (line, d)))
+        .filter(|(line, d)| {
+            // The vehicle is given when it serves another line than the one it is listed under.
+            let (name, towards) = d
+                .vehicle
+                .as_ref()
+                .map_or((&line.name, &line.towards), |v| (&v.name, &v.towards));
+
+            line_matches(name, &lio.line) && direction_matches(towards, &lio.direction)
+        })
+        .map(|(_, d)| d)
+        .collect::<Vec<&Departure>>()
+}
+
+/// Compares line names exactly, ignoring case and whitespace, so "1" matches neither "10" nor
+/// "D1x". Names listed together in `LINE_ALIASES` are considered equal.
+fn line_matches(name: &str, lio_line: &str) -> bool {
+    let name = normalize_line_name(name);
+    let lio_line = normalize_line_name(lio_line);
+
+    name == lio_line
+        || LINE_ALIASES.iter().any(|aliases| {
+            aliases.iter().any(|a| normalize_line_name(a) == name)
+                && aliases.iter().any(|a| normalize_line_name(a) == lio_line)
         })
-        .next()
-        .map(|line| *line)
 }
 
-fn lio_line_pair_to_trip_dto(pair: &(&IntervalLio, Option<&Line>)) -> TripDto {
-    let (lio, line) = pair;
+fn normalize_line_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn direction_matches(towards: &str, lio_direction: &str) -> bool {
+    towards
+        .trim()
+        .to_lowercase()
+        .contains(&lio_direction.trim().to_lowercase())
+}
+
+fn lio_departures_pair_to_trip_dto(pair: &(&IntervalLio, Vec<&Departure>)) -> TripDto {
+    let (lio, departures) = pair;
+
+    let mut departures = departures
+        .iter()
+        .map(|d| line_departure_to_departure_dto(d, lio.foot_minutes_to_station))
+        .collect::<Vec<DepartureDto>>();
+    // Departures from several platforms are merged into one list.
+    departures.sort_by_key(|d| d.countdown);
 
     TripDto {
         line: lio.line.clone(),
         direction: lio.direction.clone(),
-        foot_minutes_to_station: 5,
-        departures: line.map_or(vec![], |l| {
-            l.departures
-                .departure
-                .iter()
-                .map(|d| line_departure_to_departure_dto(d))
-                .collect::<Vec<DepartureDto>>()
-        }),
+        foot_minutes_to_station: lio.foot_minutes_to_station,
+        departures,
+        stale: false,
+        age_seconds: None,
+        alerts: vec![],
     }
 }
 
-fn line_departure_to_departure_dto(d: &Departure) -> DepartureDto {
+fn line_departure_to_departure_dto(d: &Departure, foot_minutes_to_station: i32) -> DepartureDto {
     let real_time = d
         .clone()
         .vehicle
         .map(|v| v.realtime_supported)
         .unwrap_or(false);
 
-    let late = if !real_time {
-        false
-    } else {
-        d.clone()
-            .departure_time
-            .time_real
-            .map(|tr| {
-                let time_real = tr.parse::<DateTime<Utc>>().unwrap();
-                let time_planned = d
-                    .departure_time
-                    .time_planned
-                    .parse::<DateTime<Utc>>()
-                    .unwrap();
-
-                time_real > time_planned
-            })
-            .unwrap_or(false)
-    };
+    // Malformed times from upstream are treated like missing ones, the countdown is still known.
+    let planned_time = d.departure_time.time_planned.parse::<DateTime<Utc>>().ok();
+    let expected_time = d
+        .departure_time
+        .time_real
+        .as_ref()
+        .and_then(|tr| tr.parse::<DateTime<Utc>>().ok());
+
+    let late = real_time
+        && planned_time
+            .zip(expected_time)
+            .is_some_and(|(planned, expected)| expected > planned);
 
     DepartureDto {
         direction: d.clone().vehicle.map(|v| v.towards.trim().to_string()),
+        planned_time: planned_time.map(|t| t.with_timezone(&Vienna).fixed_offset()),
+        expected_time: expected_time.map(|t| t.with_timezone(&Vienna).fixed_offset()),
         countdown: d.departure_time.countdown,
-        real_time: real_time,
-        late: late,
+        leave_in: d.departure_time.countdown - foot_minutes_to_station,
+        real_time,
+        late,
         traffic_jam: d.clone().vehicle.map(|v| v.traffic_jam).unwrap_or(false),
+        cancelled: false,
+        platform: None,
+        platform_changed: false,
+        delay_seconds: planned_time.zip(expected_time).map(|(planned, expected)| {
+            expected.signed_duration_since(planned).num_seconds() as i32
+        }),
+        remarks: vec![],
     }
 }
-
-// fn map_line_to_lio<'a>(
-//     lios: &'a Vec<&IntervalLio>,
-//     line: &'a Line,
-// ) -> Option<(&'a IntervalLio, &'a Line)> {
-//     lios.iter()
-//         .filter(|l| {
-//             line.name
-//                 .trim()
-//                 .to_lowercase()
-//                 .contains(&l.line.to_lowercase())
-//                 && line
-//                     .towards
-//                     .trim()
-//                     .to_lowercase()
-//                     .contains(&l.direction.to_lowercase())
-//         })
-//         .map(|l| (*l, line))
-//         .next()
-// }
-
-fn lios_target_line(lios: &Vec<&IntervalLio>, line: &Line) -> bool {
-    lios.iter().any(|l| {
-        line.name
-            .trim()
-            .to_lowercase()
-            .contains(&l.line.to_lowercase())
-            && line
-                .towards
-                .trim()
-                .to_lowercase()
-                .contains(&l.direction.to_lowercase())
-    })
-}
-
-pub fn filter_monitors_for_lios(monitors: &Vec<Monitor>, lios: &Vec<&IntervalLio>) -> Vec<Monitor> {
-    let is_line_targeted = |line: &Line| lios_target_line(lios, line);
-
-    monitors
-        .iter()
-        .filter(|m| m.lines.first().is_some_and(is_line_targeted))
-        .cloned()
-        .collect::<Vec<Monitor>>()
-}
-
-pub fn format_monitors_plain(monitors: &Vec<Monitor>) -> Vec<String> {
-    monitors
-        .iter()
-        .map(|m| {
-            format!(
-                "{:3} -> {:20} in {:3} minutes",
-                m.lines.first().unwrap().name.trim(),
-                m.lines.first().unwrap().towards.trim(),
-                m.lines.first().unwrap().departures.departure[0]
-                    .departure_time
-                    .countdown,
-            )
-        })
-        .collect::<Vec<String>>()
-}
@@ -0,0 +1,220 @@
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppState,
+    models::internal::Station,
+    services::{capture::UpstreamClient, provider::ProviderError, wl::get_stations},
+};
+
+/// How long to wait before trying again after the station list could not be downloaded.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// The Wiener Linien station list as it was downloaded at `fetched_at`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StationSnapshot {
+    pub fetched_at: DateTime<Utc>,
+    pub stations: Vec<Station>,
+}
+
+/// Stations that appeared in or disappeared from the station list with a refresh.
+#[derive(Debug, Default)]
+pub struct StationListChanges {
+    pub added: Vec<Station>,
+    pub removed: Vec<Station>,
+}
+
+struct StoreState {
+    stations: Arc<Vec<Station>>,
+    fetched_at: Option<DateTime<Utc>>,
+    refresh_error: Option<String>,
+}
+
+/// The current Wiener Linien station list, shared between the provider and the background
+/// refresh. Every successful download is also written to `snapshot_path`, so the server can
+/// start with the last known list while wienerlinien.at is unreachable.
+#[derive(Clone)]
+pub struct StationStore {
+    state: Arc<RwLock<StoreState>>,
+    stations_url: String,
+    snapshot_path: PathBuf,
+}
+
+impl StationStore {
+    /// Downloads the station list, falling back to the snapshot on disk and finally to an empty
+    /// list if that fails too.
+    pub async fn load(
+        client: &UpstreamClient,
+        stations_url: String,
+        snapshot_path: PathBuf,
+    ) -> Self {
+        let store = StationStore {
+            state: Arc::new(RwLock::new(StoreState {
+                stations: Arc::default(),
+                fetched_at: None,
+                refresh_error: None,
+            })),
+            stations_url,
+            snapshot_path,
+        };
+
+        let Err(e) = store.refresh(client).await else {
+            return store;
+        };
+        tracing::warn!("Error downloading Wiener Linien stations: {}", e);
+
+        match read_snapshot(&store.snapshot_path).await {
+            Ok(snapshot) => {
+                tracing::warn!(
+                    "Using Wiener Linien stations from {}",
+                    snapshot.fetched_at.to_rfc3339()
+                );
+                store.replace(snapshot);
+                store.state.write().unwrap().refresh_error = Some(e.to_string());
+            }
+            Err(snapshot_error) => tracing::error!(
+                "Error reading Wiener Linien station snapshot {}: {}, starting without stations",
+                store.snapshot_path.display(),
+                snapshot_error
+            ),
+        }
+
+        store
+    }
+
+    pub fn stations(&self) -> Arc<Vec<Station>> {
+        self.state.read().unwrap().stations.clone()
+    }
+
+    pub fn fetched_at(&self) -> Option<DateTime<Utc>> {
+        self.state.read().unwrap().fetched_at
+    }
+
+    /// The error of the last download, if it failed.
+    pub fn refresh_error(&self) -> Option<String> {
+        self.state.read().unwrap().refresh_error.clone()
+    }
+
+    /// Downloads the station list, swaps it in and writes it to the snapshot file.
+    pub async fn refresh(
+        &self,
+        client: &UpstreamClient,
+    ) -> Result<StationListChanges, ProviderError> {
+        let snapshot = match get_stations(client, &self.stations_url).await {
+            Ok(stations) => StationSnapshot {
+                fetched_at: Utc::now(),
+                stations,
+            },
+            Err(e) => {
+                self.state.write().unwrap().refresh_error = Some(e.to_string());
+                return Err(e);
+            }
+        };
+
+        if let Err(e) = write_snapshot(&self.snapshot_path, &snapshot).await {
+            tracing::warn!(
+                "Error writing Wiener Linien station snapshot {}: {}",
+                self.snapshot_path.display(),
+                e
+            );
+        }
+
+        Ok(self.replace(snapshot))
+    }
+
+    fn replace(&self, snapshot: StationSnapshot) -> StationListChanges {
+        let mut state = self.state.write().unwrap();
+        let changes = diff_stations(&state.stations, &snapshot.stations);
+        let initial = state.fetched_at.is_none();
+
+        state.stations = Arc::new(snapshot.stations);
+        state.fetched_at = Some(snapshot.fetched_at);
+        state.refresh_error = None;
+        drop(state);
+
+        // Every station is new on the first load, which is not worth listing.
+        if !initial {
+            log_changes(&changes);
+        }
+
+        changes
+    }
+}
+
+/// Refreshes the station list every `interval`, retrying sooner while downloads fail.
+pub async fn run(app_state: AppState, interval: Duration) {
+    let store = &app_state.wl_stations;
+
+    loop {
+        let wait = if store.refresh_error().is_some() || store.fetched_at().is_none() {
+            RETRY_INTERVAL
+        } else {
+            interval
+        };
+        tokio::time::sleep(wait).await;
+
+        if let Err(e) = store.refresh(&app_state.upstream).await {
+            tracing::warn!("Error refreshing Wiener Linien stations: {}", e);
+        }
+    }
+}
+
+fn diff_stations(old: &[Station], new: &[Station]) -> StationListChanges {
+    let old_ids = old.iter().map(|s| s.id.as_str()).collect::<HashSet<&str>>();
+    let new_ids = new.iter().map(|s| s.id.as_str()).collect::<HashSet<&str>>();
+
+    StationListChanges {
+        added: new
+            .iter()
+            .filter(|s| !old_ids.contains(s.id.as_str()))
+            .cloned()
+            .collect(),
+        removed: old
+            .iter()
+            .filter(|s| !new_ids.contains(s.id.as_str()))
+            .cloned()
+            .collect(),
+    }
+}
+
+fn log_changes(changes: &StationListChanges) {
+    for station in &changes.added {
+        tracing::info!(
+            "Wiener Linien station added: {} ({})",
+            station.name,
+            station.id
+        );
+    }
+    for station in &changes.removed {
+        tracing::info!(
+            "Wiener Linien station removed: {} ({})",
+            station.name,
+            station.id
+        );
+    }
+    tracing::info!(
+        "Refreshed Wiener Linien stations: {} added, {} removed",
+        changes.added.len(),
+        changes.removed.len()
+    );
+}
+
+async fn read_snapshot(path: &PathBuf) -> Result<StationSnapshot, ProviderError> {
+    Ok(serde_json::from_slice(&tokio::fs::read(path).await?)?)
+}
+
+async fn write_snapshot(path: &PathBuf, snapshot: &StationSnapshot) -> Result<(), ProviderError> {
+    // Write to a temporary file first so a crash never leaves a truncated snapshot behind.
+    let tmp_path = path.with_extension("tmp");
+    tokio::fs::write(&tmp_path, serde_json::to_vec(snapshot)?).await?;
+    tokio::fs::rename(&tmp_path, path).await?;
+
+    Ok(())
+}
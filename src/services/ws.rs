@@ -0,0 +1,203 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use axum::{
+    extract::{
+        State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    response::Response,
+};
+
+use crate::{
+    AppState,
+    dtos::internal::{TripDto, WsClientMessage, WsServerMessage},
+    models::internal::IntervalLio,
+    services::{
+        boards::{select_board, select_board_timetable_lios},
+        internal::select_interval_lios,
+    },
+};
+
+/// How often subscribed trips are re-checked even if the cache did not change, so countdowns
+/// keep ticking.
+const TICK_INTERVAL: Duration = Duration::from_secs(60);
+
+pub async fn ws_handler(ws: WebSocketUpgrade, State(app_state): State<AppState>) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(socket, app_state))
+}
+
+/// LIOs a client subscribed to directly and boards whose LIOs it follows. Board LIOs are looked
+/// up on every update, so LIOs added to a board later are sent as well.
+#[derive(Default)]
+struct Subscriptions {
+    lio_ids: HashSet<String>,
+    board_ids: HashSet<String>,
+}
+
+/// Sends a `trip` message for every subscribed LIO whose cached trip changed since it was last
+/// sent to this client. Trips are only read from the cache, never fetched for a client.
+async fn handle_socket(mut socket: WebSocket, app_state: AppState) {
+    let mut subscriptions = Subscriptions::default();
+    let mut last_sent: HashMap<String, TripDto> = HashMap::new();
+    let mut updates = app_state.trip_cache.subscribe();
+    let mut ticker = tokio::time::interval(TICK_INTERVAL);
+
+    loop {
+        tokio::select! {
+            message = socket.recv() => {
+                let Some(Ok(message)) = message else {
+                    break;
+                };
+
+                match message {
+                    Message::Text(text) => match serde_json::from_str::<WsClientMessage>(&text) {
+                        Ok(WsClientMessage::Subscribe { lio_ids, board_id }) => {
+                            subscriptions.lio_ids.extend(lio_ids);
+                            subscriptions.board_ids.extend(board_id);
+                        }
+                        Ok(WsClientMessage::Unsubscribe { lio_ids, board_id }) => {
+                            for id in lio_ids {
+                                subscriptions.lio_ids.remove(&id);
+                                last_sent.remove(&id);
+                            }
+                            if let Some(board_id) = board_id {
+                                subscriptions.board_ids.remove(&board_id);
+                            }
+                        }
+                        Err(e) => {
+                            let error = WsServerMessage::Error {
+                                message: format!("Invalid message: {}", e),
+                            };
+                            if send(&mut socket, &error).await.is_err() {
+                                break;
+                            }
+                        }
+                    },
+                    Message::Close(_) => break,
+                    _ => continue,
+                }
+            }
+            _ = ticker.tick() => {}
+            changed = updates.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+            }
+        }
+
+        if send_updates(&mut socket, &app_state, &mut subscriptions, &mut last_sent)
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+async fn send_updates(
+    socket: &mut WebSocket,
+    app_state: &AppState,
+    subscriptions: &mut Subscriptions,
+    last_sent: &mut HashMap<String, TripDto>,
+) -> Result<(), axum::Error> {
+    if subscriptions.lio_ids.is_empty()
+        && subscriptions.board_ids.is_empty()
+        && last_sent.is_empty()
+    {
+        return Ok(());
+    }
+
+    // Board LIOs carry the walking times of their board like on its timetable. A LIO subscribed
+    // through several boards, or directly as well, is sent as seen from one of its boards.
+    let mut lios: Vec<IntervalLio> = Vec::new();
+    for board_id in &subscriptions.board_ids {
+        let board_lios = match select_board(&app_state.pool, board_id).await {
+            Ok(Some(board)) => select_board_timetable_lios(app_state, &board).await,
+            Ok(None) => Ok(vec![]),
+            Err(e) => Err(e),
+        };
+
+        match board_lios {
+            Ok(board_lios) => {
+                for lio in board_lios {
+                    if !lios.iter().any(|l| l.id == lio.id) {
+                        lios.push(lio);
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::error!("Error loading LIOs of board {}: {}", board_id, e);
+                return Ok(());
+            }
+        }
+    }
+
+    match select_interval_lios(&app_state.pool).await {
+        Ok(all_lios) => {
+            for lio in all_lios {
+                if subscriptions.lio_ids.contains(&lio.id) && !lios.iter().any(|l| l.id == lio.id) {
+                    lios.push(lio);
+                }
+            }
+        }
+        Err(e) => {
+            tracing::error!("Error loading LIOs for websocket: {}", e);
+            return Ok(());
+        }
+    }
+
+    let mut messages = Vec::new();
+    for lio in &lios {
+        if let Some(trip) = app_state.trip_cache.trip(lio)
+            && last_sent.get(&lio.id) != Some(&trip)
+        {
+            last_sent.insert(lio.id.clone(), trip.clone());
+            messages.push(WsServerMessage::Trip {
+                lio_id: lio.id.clone(),
+                alerts: trip.alerts.clone(),
+                trip,
+            });
+        }
+    }
+
+    let missing = subscriptions
+        .lio_ids
+        .iter()
+        .filter(|id| !lios.iter().any(|lio| lio.id == **id))
+        .cloned()
+        .collect::<Vec<String>>();
+    for id in missing {
+        subscriptions.lio_ids.remove(&id);
+        messages.push(match last_sent.remove(&id) {
+            Some(_) => WsServerMessage::Removed { lio_id: id },
+            None => WsServerMessage::Error {
+                message: format!("LIO '{}' not found.", id),
+            },
+        });
+    }
+
+    // LIOs that were deleted or taken off a subscribed board.
+    let dropped = last_sent
+        .keys()
+        .filter(|id| !lios.iter().any(|lio| lio.id == **id))
+        .cloned()
+        .collect::<Vec<String>>();
+    for id in dropped {
+        last_sent.remove(&id);
+        messages.push(WsServerMessage::Removed { lio_id: id });
+    }
+
+    for message in &messages {
+        send(socket, message).await?;
+    }
+
+    Ok(())
+}
+
+async fn send(socket: &mut WebSocket, message: &WsServerMessage) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(message).expect("websocket messages serialize to JSON");
+    socket.send(Message::Text(text.into())).await
+}
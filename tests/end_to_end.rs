@@ -0,0 +1,213 @@
+//! End-to-end tests of the server against recorded upstream responses, run them with
+//! `db/run-end-to-end-tests.sh`. See `common` for the setup.
+
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::{Value, json};
+
+use common::TestEnv;
+
+fn wl_lio() -> Value {
+    json!({
+        "provider": "Wiener Linien",
+        "station": "Karlsplatz",
+        "line": "U1",
+        "direction": "Leopoldau",
+        "foot_minutes_to_station": 1
+    })
+}
+
+fn oebb_lio() -> Value {
+    json!({
+        "provider": "OEBB",
+        "station": "Wien Meidling",
+        "line": "S1",
+        "direction": "Wiener Neustadt",
+        "foot_minutes_to_station": 3
+    })
+}
+
+fn trip<'a>(timetable: &'a Value, line: &str) -> &'a Value {
+    timetable["trips"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|t| t["line"] == line)
+        .unwrap_or_else(|| panic!("No trip for line {} in {}", line, timetable))
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn wl_lio_shows_departures_and_alerts() {
+    let env = TestEnv::start().await;
+
+    let (status, lio) = env.post("/lio", wl_lio()).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", lio);
+    assert_eq!(lio["station"], "Karlsplatz");
+    assert_eq!(lio["foot_minutes_estimated"], false);
+
+    let (status, lios) = env.get("/lio").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(lios.as_array().unwrap().len(), 1);
+    assert_eq!(lios[0]["id"], lio["id"]);
+
+    let (status, timetable) = env.get("/timetable").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(timetable["message"], Value::Null);
+
+    let trip = trip(&timetable, "U1");
+    assert_eq!(trip["direction"], "Leopoldau");
+    assert_eq!(trip["stale"], false);
+
+    let departures = trip["departures"].as_array().unwrap();
+    assert_eq!(departures.len(), 2);
+    assert_eq!(departures[0]["countdown"], 2);
+    assert_eq!(departures[0]["leave_in"], 1);
+    assert_eq!(departures[0]["real_time"], true);
+    assert_eq!(departures[0]["late"], true);
+    assert_eq!(departures[0]["delay_seconds"], 60);
+    assert_eq!(departures[0]["planned_time"], "2025-07-10T08:02:00+02:00");
+    assert_eq!(departures[0]["expected_time"], "2025-07-10T08:03:00+02:00");
+    assert_eq!(departures[1]["countdown"], 6);
+    assert_eq!(departures[1]["leave_in"], 5);
+    assert_eq!(departures[1]["late"], false);
+
+    let mut alert_ids = timetable["alerts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| a["id"].as_str().unwrap())
+        .collect::<Vec<&str>>();
+    alert_ids.sort();
+    assert_eq!(alert_ids, ["ftp_aufzug_karlsplatz", "ftp_stoerung_u1"]);
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn departures_out_of_walking_reach_are_hidden() {
+    let env = TestEnv::start().await;
+
+    let mut lio = wl_lio();
+    lio["foot_minutes_to_station"] = json!(4);
+    let (status, lio) = env.post("/lio", lio).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", lio);
+
+    // The U1 leaving in 2 minutes cannot be reached in 4.
+    let (_, timetable) = env.get("/timetable").await;
+    let departures = trip(&timetable, "U1")["departures"]
+        .as_array()
+        .unwrap()
+        .clone();
+    assert_eq!(departures.len(), 1);
+    assert_eq!(departures[0]["countdown"], 6);
+    assert_eq!(departures[0]["leave_in"], 2);
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn wl_lio_merges_departures_of_all_platforms() {
+    let env = TestEnv::start().await;
+
+    // Line 1 is the second line of one platform's monitor and the only line of another, next to
+    // line N1 on a third platform.
+    let (status, lio) = env
+        .post(
+            "/lio",
+            json!({
+                "provider": "Wiener Linien",
+                "station": "Schwedenplatz",
+                "line": "1",
+                "direction": "Prater Hauptallee",
+                "foot_minutes_to_station": 0
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", lio);
+
+    let (_, timetable) = env.get("/timetable").await;
+    let countdowns = trip(&timetable, "1")["departures"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d["countdown"].as_i64().unwrap())
+        .collect::<Vec<i64>>();
+    assert_eq!(countdowns, [3, 7]);
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn oebb_lio_shows_platforms_delays_and_cancellations() {
+    let env = TestEnv::start().await;
+
+    let (status, lio) = env.post("/lio", oebb_lio()).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", lio);
+
+    let (status, timetable) = env.get("/timetable").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let trip = trip(&timetable, "S1");
+    let departures = trip["departures"].as_array().unwrap();
+    assert_eq!(departures.len(), 2);
+
+    assert_eq!(departures[0]["direction"], "Wiener Neustadt Hbf");
+    assert_eq!(departures[0]["countdown"], 14);
+    assert_eq!(departures[0]["leave_in"], 11);
+    assert_eq!(departures[0]["platform"], "3");
+    assert_eq!(departures[0]["platform_changed"], true);
+    assert_eq!(departures[0]["delay_seconds"], 120);
+    assert_eq!(departures[0]["cancelled"], false);
+    assert_eq!(
+        departures[0]["remarks"],
+        json!(["Bauarbeiten zwischen Mödling und Baden"])
+    );
+
+    assert_eq!(departures[1]["countdown"], 42);
+    assert_eq!(departures[1]["cancelled"], true);
+    assert_eq!(departures[1]["real_time"], false);
+    assert_eq!(departures[1]["platform"], "2");
+    assert_eq!(departures[1]["platform_changed"], false);
+    assert_eq!(departures[1]["delay_seconds"], Value::Null);
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn lio_with_unknown_line_is_rejected() {
+    let env = TestEnv::start().await;
+
+    let mut lio = wl_lio();
+    lio["line"] = json!("U2");
+    let (status, error) = env.post("/lio", lio).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        error["message"].as_str().unwrap().contains("'U2'"),
+        "{}",
+        error
+    );
+
+    let (_, lios) = env.get("/lio").await;
+    assert_eq!(lios, json!([]));
+}
+
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL"]
+async fn deleted_lio_leaves_timetable() {
+    let env = TestEnv::start().await;
+
+    let (_, wl) = env.post("/lio", wl_lio()).await;
+    let (_, _oebb) = env.post("/lio", oebb_lio()).await;
+
+    let (_, timetable) = env.get("/timetable").await;
+    assert_eq!(timetable["trips"].as_array().unwrap().len(), 2);
+
+    let status = env
+        .delete(&format!("/lio/{}", wl["id"].as_str().unwrap()))
+        .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, timetable) = env.get("/timetable").await;
+    let trips = timetable["trips"].as_array().unwrap();
+    assert_eq!(trips.len(), 1);
+    assert_eq!(trips[0]["line"], "S1");
+    assert_eq!(timetable["alerts"], json!([]));
+}